@@ -0,0 +1,174 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, TimeZone};
+use git2::build::CheckoutBuilder;
+use git2::{BranchType, ErrorCode, Oid, Repository};
+
+/// A local branch and how it relates to its upstream and the default branch.
+#[derive(Debug, Clone)]
+pub struct BranchInfo {
+    pub name: String,
+    pub tip: Oid,
+    pub is_head: bool,
+    /// The upstream's short name, e.g. `origin/main`, if one is configured.
+    pub upstream: Option<String>,
+    /// An upstream is configured but no longer exists, typically because the
+    /// branch was deleted on the remote.
+    pub upstream_gone: bool,
+    pub ahead: usize,
+    pub behind: usize,
+    pub last_commit_time: DateTime<Local>,
+    pub last_commit_summary: String,
+    /// Already reachable from the default branch.
+    pub merged: bool,
+}
+
+/// All local branches, most recently committed to first.
+pub fn list(repo: &Repository) -> Result<Vec<BranchInfo>> {
+    let default = default_branch(repo);
+    let mut branches = Vec::new();
+
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let Ok(commit) = branch.get().peel_to_commit() else {
+            continue;
+        };
+        let refname = branch.get().name().unwrap_or_default().to_string();
+
+        let upstream_ref = repo
+            .branch_upstream_name(&refname)
+            .ok()
+            .and_then(|upstream| upstream.as_str().map(str::to_string));
+        let (upstream, upstream_gone, ahead, behind) = match &upstream_ref {
+            Some(upstream) => {
+                let short = upstream.strip_prefix("refs/remotes/").unwrap_or(upstream).to_string();
+                match repo.refname_to_id(upstream) {
+                    Ok(id) => {
+                        let (ahead, behind) = repo.graph_ahead_behind(commit.id(), id)?;
+                        (Some(short), false, ahead, behind)
+                    }
+                    Err(e) if e.code() == ErrorCode::NotFound => (Some(short), true, 0, 0),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            None => (None, false, 0, 0),
+        };
+
+        // The default branch, and local branches tracking it, aren't merged
+        // into it even when behind.
+        let merged = match &default {
+            Some((default_name, tip))
+                if *default_name != refname && upstream_ref.as_ref() != Some(default_name) =>
+            {
+                *tip == commit.id() || repo.graph_descendant_of(*tip, commit.id())?
+            }
+            _ => false,
+        };
+
+        branches.push(BranchInfo {
+            name,
+            tip: commit.id(),
+            is_head: branch.is_head(),
+            upstream,
+            upstream_gone,
+            ahead,
+            behind,
+            last_commit_time: Local
+                .timestamp_opt(commit.time().seconds(), 0)
+                .single()
+                .unwrap_or_else(Local::now),
+            last_commit_summary: commit.summary().unwrap_or_default().to_string(),
+            merged,
+        });
+    }
+
+    branches.sort_by_key(|branch| std::cmp::Reverse(branch.last_commit_time));
+    Ok(branches)
+}
+
+// The branch others get merged into: whatever `origin/HEAD` points at, or
+// else a local `main` or `master`. Returns its ref name and tip.
+fn default_branch(repo: &Repository) -> Option<(String, Oid)> {
+    let remote_head = repo
+        .find_reference("refs/remotes/origin/HEAD")
+        .ok()
+        .and_then(|reference| reference.symbolic_target().map(str::to_string));
+    let candidates = remote_head
+        .into_iter()
+        .chain(["refs/heads/main".to_string(), "refs/heads/master".to_string()]);
+
+    candidates
+        .filter_map(|name| {
+            let id = repo.refname_to_id(&name).ok()?;
+            Some((name, id))
+        })
+        .next()
+}
+
+/// Switches to branch `name`, refusing to overwrite local changes that
+/// conflict with it.
+pub fn checkout(repo: &Repository, name: &str) -> Result<()> {
+    let branch = repo.find_branch(name, BranchType::Local)?;
+    if branch.is_head() {
+        bail!("Already on '{}'", name);
+    }
+    let refname = branch.get().name().context("Branch name is not valid UTF-8")?.to_string();
+    let commit = branch.get().peel_to_commit()?;
+
+    repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+        .with_context(|| format!("Cannot switch to '{}'", name))?;
+    repo.set_head(&refname)?;
+    Ok(())
+}
+
+/// Creates branch `name` pointing at commit `target`.
+pub fn create(repo: &Repository, name: &str, target: Oid) -> Result<()> {
+    check_name(name)?;
+    let commit = repo.find_commit(target)?;
+    repo.branch(name, &commit, false)
+        .with_context(|| format!("Cannot create branch '{}'", name))?;
+    Ok(())
+}
+
+pub fn rename(repo: &Repository, name: &str, new_name: &str) -> Result<()> {
+    check_name(new_name)?;
+    repo.find_branch(name, BranchType::Local)?
+        .rename(new_name, false)
+        .with_context(|| format!("Cannot rename '{}' to '{}'", name, new_name))?;
+    Ok(())
+}
+
+/// Deletes branch `name`. Unless `force` is set, only a branch whose work
+/// is merged into its upstream, or into HEAD if it has none, is deleted.
+pub fn delete(repo: &Repository, name: &str, force: bool) -> Result<()> {
+    let mut branch = repo.find_branch(name, BranchType::Local)?;
+    if branch.is_head() {
+        bail!("Cannot delete the branch you are on");
+    }
+    if !force && !is_merged(repo, name)? {
+        bail!("Branch '{}' is not fully merged", name);
+    }
+    branch.delete()?;
+    Ok(())
+}
+
+/// Whether deleting branch `name` would lose no commits, the way `git branch
+/// -d` decides it.
+pub fn is_merged(repo: &Repository, name: &str) -> Result<bool> {
+    let branch = repo.find_branch(name, BranchType::Local)?;
+    let tip = branch.get().peel_to_commit()?.id();
+    let base = match branch.upstream() {
+        Ok(upstream) => upstream.get().peel_to_commit()?.id(),
+        Err(_) => repo.head()?.peel_to_commit()?.id(),
+    };
+    Ok(base == tip || repo.graph_descendant_of(base, tip)?)
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if !git2::Branch::name_is_valid(name)? {
+        bail!("'{}' is not a valid branch name", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use git2::{Oid, Repository};
+
+/// Commits the index with `message`, or rewrites HEAD with it when
+/// `amend` is set. Runs the `pre-commit` and `commit-msg` hooks first, as
+/// `git commit` would; libgit2 doesn't run hooks itself.
+pub fn create(repo: &Repository, message: &str, amend: bool) -> Result<Oid> {
+    let signature = repo
+        .signature()
+        .context("No signature configured; set user.name and user.email")?;
+
+    let head = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut parents = head.into_iter().collect::<Vec<_>>();
+    if amend && parents.is_empty() {
+        bail!("There is no commit to amend yet");
+    }
+    if !amend {
+        for id in merge_heads(repo) {
+            parents.push(repo.find_commit(id)?);
+        }
+        let staged = match parents.as_slice() {
+            [] => !index_tree(repo)?.1.is_empty(),
+            [head] => head.tree_id() != index_tree(repo)?.0,
+            _ => true,
+        };
+        if !staged {
+            bail!("Nothing staged to commit");
+        }
+    }
+
+    // An empty message fails before any hook gets to run.
+    let message = clean_message(message)?;
+    run_hook(repo, "pre-commit", &[])?;
+    let message = commit_msg_hook(repo, &message)?;
+    let message = clean_message(&message)?;
+
+    // Re-read after the hooks, which may have staged more.
+    let tree = repo.find_tree(index_tree(repo)?.0)?;
+    let id = if amend {
+        parents[0].amend(Some("HEAD"), None, Some(&signature), None, Some(&message), Some(&tree))?
+    } else {
+        let parents = parents.iter().collect::<Vec<_>>();
+        let id = repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)?;
+        if parents.len() > 1 {
+            repo.cleanup_state()?;
+        }
+        id
+    };
+
+    // Like git, a failing post-commit hook doesn't undo the commit.
+    run_hook(repo, "post-commit", &[]).ok();
+    Ok(id)
+}
+
+/// The full message of HEAD, to start an amend from.
+pub fn head_message(repo: &Repository) -> Result<String> {
+    let head = repo.head()?.peel_to_commit()?;
+    Ok(head.message().unwrap_or_default().to_string())
+}
+
+// Strips comments and surplus whitespace the way `git commit` does.
+fn clean_message(message: &str) -> Result<String> {
+    let message = git2::message_prettify(message, Some(b'#'))?;
+    if message.trim().is_empty() {
+        bail!("Aborting commit due to empty commit message");
+    }
+    Ok(message)
+}
+
+// Writes the index out as a tree.
+fn index_tree(repo: &Repository) -> Result<(Oid, git2::Index)> {
+    let mut index = repo.index()?;
+    index.read(false)?;
+    let tree = index
+        .write_tree()
+        .context("Cannot commit with unresolved conflicts")?;
+    Ok((tree, index))
+}
+
+// The other parents of a merge in progress.
+fn merge_heads(repo: &Repository) -> Vec<Oid> {
+    fs::read_to_string(repo.path().join("MERGE_HEAD"))
+        .map(|heads| heads.lines().filter_map(|line| Oid::from_str(line.trim()).ok()).collect())
+        .unwrap_or_default()
+}
+
+// Passes the message through `commit-msg`, which may rewrite it in place.
+fn commit_msg_hook(repo: &Repository, message: &str) -> Result<String> {
+    if hook_path(repo, "commit-msg").is_none() {
+        return Ok(message.to_string());
+    }
+    let file = repo.path().join("COMMIT_EDITMSG");
+    fs::write(&file, message)?;
+    run_hook(repo, "commit-msg", &[&file])?;
+    Ok(fs::read_to_string(&file)?)
+}
+
+fn run_hook(repo: &Repository, name: &str, args: &[&Path]) -> Result<()> {
+    let Some(hook) = hook_path(repo, name) else {
+        return Ok(());
+    };
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+    let output = Command::new(&hook)
+        .args(args)
+        .current_dir(workdir)
+        .output()
+        .with_context(|| format!("Failed to run the {} hook", name))?;
+
+    if !output.status.success() {
+        let mut details = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if details.is_empty() {
+            details = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        }
+        bail!("{} hook failed ({}): {}", name, output.status, details);
+    }
+    Ok(())
+}
+
+fn hook_path(repo: &Repository, name: &str) -> Option<PathBuf> {
+    let configured = repo
+        .config()
+        .ok()
+        .and_then(|config| config.get_path("core.hooksPath").ok());
+    let dir = match configured {
+        Some(dir) if dir.is_relative() => repo.workdir().unwrap_or_else(|| repo.path()).join(dir),
+        Some(dir) => dir,
+        None => repo.path().join("hooks"),
+    };
+    let hook = dir.join(name);
+    is_executable(&hook).then_some(hook)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,159 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use anyhow::{anyhow, Context, Result};
+use git2::Oid;
+
+use crate::git::diff::{DiffSource, FileDiff};
+use crate::git::repository::{GitRepository, RenameDetection};
+use crate::git::status::CommitDetail;
+
+/// What the loader thread sends back, tagged with the generation of the
+/// request it answers.
+pub enum DetailEvent {
+    Diff {
+        generation: u64,
+        result: Result<Vec<FileDiff>>,
+    },
+    Commit {
+        generation: u64,
+        result: Result<Box<CommitDetail>>,
+    },
+}
+
+enum Request {
+    /// The diffs of some paths, one after the other: for a status entry
+    /// with both staged and unstaged changes there are two.
+    Diff {
+        generation: u64,
+        parts: Vec<(DiffSource, Vec<PathBuf>)>,
+    },
+    /// A commit's details, with the ref labels the history already found.
+    Commit {
+        generation: u64,
+        id: Oid,
+        refs: Vec<String>,
+    },
+}
+
+/// Loads what the selection shows, the diff of a file or a stash and the
+/// details of a commit, on a thread of its own with its own `Repository`,
+/// so that moving through a list never waits on libgit2. Requests of a kind
+/// that pile up while one is being served are skipped in favour of the
+/// newest.
+pub struct DetailLoader {
+    requests: Sender<Request>,
+    events: Receiver<DetailEvent>,
+    diff_generation: u64,
+    commit_generation: u64,
+}
+
+impl DetailLoader {
+    pub fn new(repo_path: &Path, rename_detection: RenameDetection) -> Result<Self> {
+        let repo = GitRepository::open(repo_path)?.with_rename_detection(rename_detection);
+        let (requests, request_rx) = channel();
+        let (event_tx, events) = channel();
+        thread::Builder::new()
+            .name("gittop-detail".to_string())
+            .spawn(move || run(repo, request_rx, event_tx))
+            .context("Failed to start detail loader")?;
+
+        Ok(Self {
+            requests,
+            events,
+            diff_generation: 0,
+            commit_generation: 0,
+        })
+    }
+
+    /// Loads the diffs of `parts`, superseding any diff still being loaded.
+    pub fn load_diff(&mut self, parts: Vec<(DiffSource, Vec<PathBuf>)>) {
+        self.diff_generation += 1;
+        let _ = self.requests.send(Request::Diff {
+            generation: self.diff_generation,
+            parts,
+        });
+    }
+
+    /// Loads the details of commit `id`, superseding any commit still being
+    /// loaded. `refs` are its labels, as `CommitInfo::refs`.
+    pub fn load_commit(&mut self, id: Oid, refs: Vec<String>) {
+        self.commit_generation += 1;
+        let _ = self.requests.send(Request::Commit {
+            generation: self.commit_generation,
+            id,
+            refs,
+        });
+    }
+
+    /// The next answer to a current request; stale ones are dropped.
+    pub fn try_recv(&mut self) -> Option<DetailEvent> {
+        while let Ok(event) = self.events.try_recv() {
+            match &event {
+                DetailEvent::Diff { generation, .. } if *generation == self.diff_generation => {}
+                DetailEvent::Commit { generation, .. } if *generation == self.commit_generation => {}
+                _ => continue,
+            }
+            return Some(event);
+        }
+        None
+    }
+}
+
+fn run(repo: GitRepository, requests: Receiver<Request>, events: Sender<DetailEvent>) {
+    // The newest request of each kind not served yet.
+    let mut diff_request = None;
+    let mut commit_request = None;
+    loop {
+        if diff_request.is_none() && commit_request.is_none() {
+            match requests.recv() {
+                Ok(request) => queue(request, &mut diff_request, &mut commit_request),
+                Err(_) => return,
+            }
+        }
+        while let Ok(request) = requests.try_recv() {
+            queue(request, &mut diff_request, &mut commit_request);
+        }
+
+        // A commit's files are diffed against the detail, so it comes first.
+        let event = if let Some(Request::Commit { generation, id, refs }) = commit_request.take() {
+            DetailEvent::Commit {
+                generation,
+                result: load("the commit", || repo.commit_detail(id, refs).map(Box::new)),
+            }
+        } else if let Some(Request::Diff { generation, parts }) = diff_request.take() {
+            DetailEvent::Diff {
+                generation,
+                result: load("the diff", || diff(&repo, &parts)),
+            }
+        } else {
+            continue;
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+fn queue(request: Request, diff: &mut Option<Request>, commit: &mut Option<Request>) {
+    match request {
+        Request::Diff { .. } => *diff = Some(request),
+        Request::Commit { .. } => *commit = Some(request),
+    }
+}
+
+// A panic fails the request instead of taking the thread down with it.
+fn load<T>(what: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| Err(anyhow!("loading {} crashed", what)))
+}
+
+fn diff(repo: &GitRepository, parts: &[(DiffSource, Vec<PathBuf>)]) -> Result<Vec<FileDiff>> {
+    let mut files = Vec::new();
+    for (source, paths) in parts {
+        let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        files.extend(repo.file_diff(*source, &paths)?);
+    }
+    Ok(files)
+}
@@ -0,0 +1,501 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use git2::{Delta, Diff, DiffLineType, Patch};
+
+/// Which two trees a diff compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSource {
+    /// HEAD → index.
+    Staged,
+    /// Index → work tree, including the full content of untracked files.
+    Unstaged,
+    /// A commit's first parent → the commit.
+    Commit(git2::Oid),
+    /// The commit a stash was made on → the stash.
+    Stash(git2::Oid),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+    /// `\ No newline at end of file`, applying to the line before it.
+    NoNewline,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+    pub content: String,
+    /// The line exactly as in the file, newline included if it has one.
+    pub raw: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub source: DiffSource,
+    pub status: Delta,
+    pub old_path: Option<PathBuf>,
+    pub new_path: Option<PathBuf>,
+    pub old_mode: u32,
+    pub new_mode: u32,
+    pub binary: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl FileDiff {
+    pub fn path(&self) -> &Path {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or(Path::new(""))
+    }
+
+    /// Collects every file in `diff` with its hunks and lines.
+    pub fn from_diff(diff: &Diff, source: DiffSource) -> Result<Vec<FileDiff>> {
+        let mut files = Vec::new();
+
+        for idx in 0..diff.deltas().len() {
+            let Some(patch) = Patch::from_diff(diff, idx)? else {
+                continue;
+            };
+            let delta = patch.delta();
+            let mut hunks = Vec::with_capacity(patch.num_hunks());
+
+            for hunk_idx in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch.hunk(hunk_idx)?;
+                let mut lines = Vec::with_capacity(line_count);
+
+                for line_idx in 0..line_count {
+                    let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                    let kind = match line.origin_value() {
+                        DiffLineType::Addition => DiffLineKind::Added,
+                        DiffLineType::Deletion => DiffLineKind::Removed,
+                        DiffLineType::ContextEOFNL
+                        | DiffLineType::AddEOFNL
+                        | DiffLineType::DeleteEOFNL => DiffLineKind::NoNewline,
+                        _ => DiffLineKind::Context,
+                    };
+                    let content = String::from_utf8_lossy(line.content());
+                    let content = content.trim_end_matches(['\n', '\r']);
+                    lines.push(if kind == DiffLineKind::NoNewline {
+                        DiffLine {
+                            kind,
+                            old_lineno: None,
+                            new_lineno: None,
+                            content: content.trim_start_matches('\n').to_string(),
+                            raw: Vec::new(),
+                        }
+                    } else {
+                        DiffLine {
+                            kind,
+                            old_lineno: line.old_lineno(),
+                            new_lineno: line.new_lineno(),
+                            content: content.to_string(),
+                            raw: line.content().to_vec(),
+                        }
+                    });
+                }
+
+                hunks.push(DiffHunk {
+                    header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                    old_start: hunk.old_start(),
+                    old_lines: hunk.old_lines(),
+                    new_start: hunk.new_start(),
+                    new_lines: hunk.new_lines(),
+                    lines,
+                });
+            }
+
+            files.push(FileDiff {
+                source,
+                status: delta.status(),
+                old_path: delta.old_file().path().map(Path::to_path_buf),
+                new_path: delta.new_file().path().map(Path::to_path_buf),
+                old_mode: u32::from(delta.old_file().mode()),
+                new_mode: u32::from(delta.new_file().mode()),
+                binary: delta.flags().is_binary(),
+                hunks,
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Builds a patch for the index out of the `selected` (hunk, line)
+    /// changes: applied forwards it stages them from an unstaged diff, and
+    /// from a staged diff it is reversed so that applying it unstages them.
+    pub fn index_patch(&self, selected: &BTreeSet<(usize, usize)>) -> Result<Vec<u8>> {
+        match self.source {
+            DiffSource::Commit(_) => bail!("Committed changes can't be staged or unstaged"),
+            DiffSource::Stash(_) => bail!("Stashed changes can't be staged or unstaged"),
+            DiffSource::Staged | DiffSource::Unstaged => {}
+        }
+        self.partial_patch(selected, self.source == DiffSource::Staged)
+    }
+
+    /// Builds a patch for the work tree that reverts the `selected` changes
+    /// of an unstaged diff.
+    pub fn discard_patch(&self, selected: &BTreeSet<(usize, usize)>) -> Result<Vec<u8>> {
+        if self.source != DiffSource::Unstaged {
+            bail!("Only unstaged changes can be discarded");
+        }
+        self.partial_patch(selected, true)
+    }
+
+    // Unselected removals stay as context and unselected additions are left
+    // out. `reverse` swaps the sides, so the patch applies to the new side.
+    fn partial_patch(&self, selected: &BTreeSet<(usize, usize)>, reverse: bool) -> Result<Vec<u8>> {
+        if self.binary {
+            bail!("Binary files can only be changed as a whole");
+        }
+        if !reverse && matches!(self.status, Delta::Renamed | Delta::Copied) {
+            bail!("Renamed files can only be staged as a whole");
+        }
+
+        let complete = self.hunks.iter().enumerate().all(|(h, hunk)| {
+            hunk.lines.iter().enumerate().all(|(i, line)| {
+                !line.kind.is_change() || selected.contains(&(h, i))
+            })
+        });
+        let path = self.path().as_os_str().as_encoded_bytes();
+        let (old, new, mode) = match (reverse, self.status) {
+            (false, Delta::Added | Delta::Untracked) => {
+                (None, Some(path), Some(format!("new file mode {:o}", self.new_mode)))
+            }
+            (true, Delta::Deleted) => {
+                (None, Some(path), Some(format!("new file mode {:o}", self.old_mode)))
+            }
+            (false, Delta::Deleted) if complete => (
+                Some(path),
+                None,
+                Some(format!("deleted file mode {:o}", self.old_mode)),
+            ),
+            (true, Delta::Added | Delta::Untracked) if complete => (
+                Some(path),
+                None,
+                Some(format!("deleted file mode {:o}", self.new_mode)),
+            ),
+            _ => (Some(path), Some(path), None),
+        };
+
+        let mut patch = b"diff --git ".to_vec();
+        patch.extend(quote_path("a/", path));
+        patch.push(b' ');
+        patch.extend(quote_path("b/", path));
+        patch.push(b'\n');
+        if let Some(mode) = mode {
+            patch.extend_from_slice(format!("{}\n", mode).as_bytes());
+        }
+        let side = |prefix: &str, path: Option<&[u8]>| {
+            path.map_or_else(|| b"/dev/null".to_vec(), |path| quote_path(prefix, path))
+        };
+        for (marker, side) in [(b"--- ", side("a/", old)), (b"+++ ", side("b/", new))] {
+            patch.extend_from_slice(marker);
+            patch.extend(side);
+            patch.push(b'\n');
+        }
+
+        let mut offset: i64 = 0;
+        let mut changed = false;
+        for (h, hunk) in self.hunks.iter().enumerate() {
+            let mut body = Vec::new();
+            let (mut old_lines, mut new_lines) = (0i64, 0i64);
+            let mut hunk_changed = false;
+            let mut kept_previous = false;
+
+            for (i, line) in hunk.lines.iter().enumerate() {
+                let is_selected = selected.contains(&(h, i));
+                let kind = match (line.kind, reverse) {
+                    (DiffLineKind::Added, true) => DiffLineKind::Removed,
+                    (DiffLineKind::Removed, true) => DiffLineKind::Added,
+                    (kind, _) => kind,
+                };
+                let origin = match kind {
+                    DiffLineKind::NoNewline => {
+                        if kept_previous {
+                            body.extend_from_slice(b"\\ No newline at end of file\n");
+                        }
+                        continue;
+                    }
+                    DiffLineKind::Removed if is_selected => b'-',
+                    DiffLineKind::Added if is_selected => b'+',
+                    DiffLineKind::Added => {
+                        kept_previous = false;
+                        continue;
+                    }
+                    DiffLineKind::Removed | DiffLineKind::Context => b' ',
+                };
+
+                match origin {
+                    b'-' => old_lines += 1,
+                    b'+' => new_lines += 1,
+                    _ => {
+                        old_lines += 1;
+                        new_lines += 1;
+                    }
+                }
+                hunk_changed |= origin != b' ';
+                kept_previous = true;
+                body.push(origin);
+                body.extend_from_slice(&line.raw);
+                if !line.raw.ends_with(b"\n") {
+                    body.push(b'\n');
+                }
+            }
+
+            if !hunk_changed {
+                continue;
+            }
+            changed = true;
+
+            let old_start = i64::from(if reverse { hunk.new_start } else { hunk.old_start });
+            let mut new_start = old_start + offset;
+            if old_lines == 0 {
+                new_start += 1;
+            }
+            if new_lines == 0 {
+                new_start -= 1;
+            }
+            offset += new_lines - old_lines;
+            patch.extend_from_slice(
+                format!(
+                    "@@ -{},{} +{},{} @@\n",
+                    old_start, old_lines, new_start, new_lines
+                )
+                .as_bytes(),
+            );
+            patch.extend_from_slice(&body);
+        }
+
+        if !changed {
+            bail!("No changes selected");
+        }
+        Ok(patch)
+    }
+}
+
+impl DiffLineKind {
+    pub fn is_change(self) -> bool {
+        matches!(self, DiffLineKind::Added | DiffLineKind::Removed)
+    }
+}
+
+// A path in a patch header. Like git, paths with control characters,
+// quotes, backslashes or non-ASCII bytes are C-quoted, prefix included:
+// `"a/caf\303\251.txt"`.
+fn quote_path(prefix: &str, path: &[u8]) -> Vec<u8> {
+    let mut quoted = Vec::with_capacity(prefix.len() + path.len() + 2);
+    let printable = |b: u8| (0x20..0x7f).contains(&b);
+    if path.iter().all(|&b| printable(b) && b != b'"' && b != b'\\') {
+        quoted.extend_from_slice(prefix.as_bytes());
+        quoted.extend_from_slice(path);
+        return quoted;
+    }
+
+    quoted.push(b'"');
+    quoted.extend_from_slice(prefix.as_bytes());
+    for &b in path {
+        match b {
+            0x07 => quoted.extend_from_slice(b"\\a"),
+            0x08 => quoted.extend_from_slice(b"\\b"),
+            b'\t' => quoted.extend_from_slice(b"\\t"),
+            b'\n' => quoted.extend_from_slice(b"\\n"),
+            0x0b => quoted.extend_from_slice(b"\\v"),
+            0x0c => quoted.extend_from_slice(b"\\f"),
+            b'\r' => quoted.extend_from_slice(b"\\r"),
+            b'"' | b'\\' => quoted.extend_from_slice(&[b'\\', b]),
+            b if !printable(b) => quoted.extend(format!("\\{:03o}", b).into_bytes()),
+            b => quoted.push(b),
+        }
+    }
+    quoted.push(b'"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE_MODE: u32 = 0o100644;
+
+    fn file(source: DiffSource, status: Delta, path: &str, hunk: DiffHunk) -> FileDiff {
+        let (old_path, new_path) = match status {
+            Delta::Added | Delta::Untracked => (None, Some(PathBuf::from(path))),
+            Delta::Deleted => (Some(PathBuf::from(path)), None),
+            _ => (Some(PathBuf::from(path)), Some(PathBuf::from(path))),
+        };
+        FileDiff {
+            source,
+            status,
+            old_path,
+            new_path,
+            old_mode: FILE_MODE,
+            new_mode: FILE_MODE,
+            binary: false,
+            hunks: vec![hunk],
+        }
+    }
+
+    // A hunk from its starts and its lines as they'd appear in a patch,
+    // e.g. `"+added"`.
+    fn hunk(old_start: u32, new_start: u32, lines: &[&str]) -> DiffHunk {
+        let (mut old, mut new) = (old_start, new_start);
+        let lines: Vec<DiffLine> = lines
+            .iter()
+            .map(|line| {
+                let (origin, content) = line.split_at(1);
+                let (kind, old_lineno, new_lineno) = match origin {
+                    "+" => (DiffLineKind::Added, None, Some(new)),
+                    "-" => (DiffLineKind::Removed, Some(old), None),
+                    _ => (DiffLineKind::Context, Some(old), Some(new)),
+                };
+                old += u32::from(old_lineno.is_some());
+                new += u32::from(new_lineno.is_some());
+                DiffLine {
+                    kind,
+                    old_lineno,
+                    new_lineno,
+                    content: content.to_string(),
+                    raw: format!("{}\n", content).into_bytes(),
+                }
+            })
+            .collect();
+        let count = |kind| lines.iter().filter(|line| line.kind != kind).count() as u32;
+        DiffHunk {
+            header: String::new(),
+            old_start,
+            old_lines: count(DiffLineKind::Added),
+            new_start,
+            new_lines: count(DiffLineKind::Removed),
+            lines,
+        }
+    }
+
+    fn lines(selected: &[usize]) -> BTreeSet<(usize, usize)> {
+        selected.iter().map(|&line| (0, line)).collect()
+    }
+
+    fn text(patch: Result<Vec<u8>>) -> String {
+        String::from_utf8(patch.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn staging_lines_from_the_middle_of_a_hunk_keeps_the_rest_as_context() {
+        let diff = file(
+            DiffSource::Unstaged,
+            Delta::Modified,
+            "notes.txt",
+            hunk(3, 3, &[" a", "-b", "+B", "+C", " d"]),
+        );
+
+        assert_eq!(
+            text(diff.index_patch(&lines(&[3]))),
+            "diff --git a/notes.txt b/notes.txt\n\
+             --- a/notes.txt\n\
+             +++ b/notes.txt\n\
+             @@ -3,3 +3,4 @@\n a\n b\n+C\n d\n"
+        );
+    }
+
+    #[test]
+    fn staging_an_added_file_creates_it() {
+        let diff = file(DiffSource::Unstaged, Delta::Untracked, "new.txt", hunk(0, 1, &["+x", "+y"]));
+
+        assert_eq!(
+            text(diff.index_patch(&lines(&[0, 1]))),
+            "diff --git a/new.txt b/new.txt\n\
+             new file mode 100644\n\
+             --- /dev/null\n\
+             +++ b/new.txt\n\
+             @@ -0,0 +1,2 @@\n+x\n+y\n"
+        );
+    }
+
+    #[test]
+    fn deleted_files_are_deleted_whole_and_restored_by_discarding() {
+        let diff = file(DiffSource::Unstaged, Delta::Deleted, "gone.txt", hunk(1, 0, &["-x", "-y"]));
+
+        assert_eq!(
+            text(diff.index_patch(&lines(&[0, 1]))),
+            "diff --git a/gone.txt b/gone.txt\n\
+             deleted file mode 100644\n\
+             --- a/gone.txt\n\
+             +++ /dev/null\n\
+             @@ -1,2 +0,0 @@\n-x\n-y\n"
+        );
+        // Staging only part of a deletion leaves the file in place.
+        assert_eq!(
+            text(diff.index_patch(&lines(&[1]))),
+            "diff --git a/gone.txt b/gone.txt\n\
+             --- a/gone.txt\n\
+             +++ b/gone.txt\n\
+             @@ -1,2 +1,1 @@\n x\n-y\n"
+        );
+        assert_eq!(
+            text(diff.discard_patch(&lines(&[0]))),
+            "diff --git a/gone.txt b/gone.txt\n\
+             new file mode 100644\n\
+             --- /dev/null\n\
+             +++ b/gone.txt\n\
+             @@ -0,0 +1,1 @@\n+x\n"
+        );
+    }
+
+    #[test]
+    fn unstaging_lines_reverses_the_staged_diff() {
+        let diff = file(
+            DiffSource::Staged,
+            Delta::Modified,
+            "notes.txt",
+            hunk(1, 1, &[" a", "-b", "+B", " c"]),
+        );
+
+        // Against the index, which holds `a B c`: only `B` goes.
+        assert_eq!(
+            text(diff.index_patch(&lines(&[2]))),
+            "diff --git a/notes.txt b/notes.txt\n\
+             --- a/notes.txt\n\
+             +++ b/notes.txt\n\
+             @@ -1,3 +1,2 @@\n a\n-B\n c\n"
+        );
+        // Unstaging the removal puts `b` back.
+        assert_eq!(
+            text(diff.index_patch(&lines(&[1]))),
+            "diff --git a/notes.txt b/notes.txt\n\
+             --- a/notes.txt\n\
+             +++ b/notes.txt\n\
+             @@ -1,3 +1,4 @@\n a\n+b\n B\n c\n"
+        );
+    }
+
+    #[test]
+    fn unusual_paths_are_quoted_like_git_does() {
+        assert_eq!(quote_path("a/", b"plain/path.txt"), b"a/plain/path.txt");
+        assert_eq!(
+            quote_path("b/", "caf\u{e9} \"x\"\t\\.txt".as_bytes()),
+            br#""b/caf\303\251 \"x\"\t\\.txt""#
+        );
+    }
+
+    #[test]
+    fn nothing_selected_is_an_error() {
+        let diff = file(DiffSource::Unstaged, Delta::Modified, "a.txt", hunk(1, 1, &[" a", "+b"]));
+        assert!(diff.index_patch(&lines(&[0])).is_err());
+    }
+}
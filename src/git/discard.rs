@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, TimeZone};
+use git2::{Commit, Index, IndexEntry, IndexTime, ObjectType, Oid, Repository, Signature};
+
+/// Discarded work tree content is kept as a chain of commits under this ref,
+/// newest first, so that it survives until it falls off the end.
+pub const DISCARD_REF: &str = "refs/gittop/discards";
+
+/// How many discards are kept before the oldest ones are dropped.
+pub const KEPT_DISCARDS: usize = 20;
+
+const FILE_MODE: u32 = 0o100644;
+const EXECUTABLE_MODE: u32 = 0o100755;
+const SYMLINK_MODE: u32 = 0o120000;
+
+/// One discard: the work tree versions of the files it threw away.
+#[derive(Debug, Clone)]
+pub struct DiscardRecord {
+    pub id: Oid,
+    pub time: DateTime<Local>,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Records the current work tree content of `paths` before they are
+/// discarded. Paths that don't exist in the work tree have nothing to lose
+/// and are skipped; returns `None` if that leaves nothing to record.
+pub fn save(repo: &Repository, paths: &[PathBuf]) -> Result<Option<Oid>> {
+    let workdir = workdir(repo)?;
+    let mut index = Index::new()?;
+    let mut saved = Vec::new();
+
+    for path in paths {
+        let full = workdir.join(path);
+        let Ok(metadata) = fs::symlink_metadata(&full) else {
+            continue;
+        };
+        let (content, mode) = if metadata.file_type().is_symlink() {
+            let target = fs::read_link(&full)?;
+            (target.to_string_lossy().into_owned().into_bytes(), SYMLINK_MODE)
+        } else if metadata.is_file() {
+            (fs::read(&full)?, file_mode(&metadata))
+        } else {
+            continue;
+        };
+        index.add(&index_entry(path, mode, repo.blob(&content)?))?;
+        saved.push(path.display().to_string());
+    }
+
+    if saved.is_empty() {
+        return Ok(None);
+    }
+
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+    let parent = latest(repo)?;
+    let message = format!("Discarded {}", saved.join(", "));
+    let signature = signature(repo)?;
+    let id = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+    )?;
+
+    let id = prune(repo, id)?;
+    repo.reference(DISCARD_REF, id, true, "gittop: discard")?;
+    Ok(Some(id))
+}
+
+/// The kept discards, newest first.
+pub fn list(repo: &Repository) -> Result<Vec<DiscardRecord>> {
+    let mut records = Vec::new();
+    let mut next = latest(repo)?;
+
+    while let Some(commit) = next {
+        if records.len() == KEPT_DISCARDS {
+            break;
+        }
+        let mut paths = Vec::new();
+        commit
+            .tree()?
+            .walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+                if entry.kind() == Some(ObjectType::Blob) {
+                    paths.push(Path::new(dir).join(entry.name().unwrap_or_default()));
+                }
+                git2::TreeWalkResult::Ok
+            })?;
+        records.push(DiscardRecord {
+            id: commit.id(),
+            time: Local
+                .timestamp_opt(commit.time().seconds(), 0)
+                .single()
+                .unwrap_or_else(Local::now),
+            paths,
+        });
+        next = commit.parents().next();
+    }
+
+    Ok(records)
+}
+
+/// Writes the files of discard `id` back into the work tree, overwriting
+/// whatever is there now.
+pub fn restore(repo: &Repository, id: Oid) -> Result<Vec<PathBuf>> {
+    let workdir = workdir(repo)?;
+    let tree = repo.find_commit(id)?.tree()?;
+    let mut files = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            let path = Path::new(dir).join(entry.name().unwrap_or_default());
+            files.push((path, entry.id(), entry.filemode() as u32));
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+
+    for (path, blob, mode) in &files {
+        let full = workdir.join(path);
+        let content = repo.find_blob(*blob)?;
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let replace = |metadata: fs::Metadata| !metadata.is_file() || *mode == SYMLINK_MODE;
+        if fs::symlink_metadata(&full).is_ok_and(replace) {
+            fs::remove_file(&full)
+                .with_context(|| format!("Failed to replace {}", path.display()))?;
+        }
+        write_file(&full, content.content(), *mode)
+            .with_context(|| format!("Failed to restore {}", path.display()))?;
+    }
+
+    Ok(files.into_iter().map(|(path, _, _)| path).collect())
+}
+
+fn latest(repo: &Repository) -> Result<Option<Commit<'_>>> {
+    match repo.find_reference(DISCARD_REF) {
+        Ok(reference) => Ok(Some(reference.peel_to_commit()?)),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Rebuilds the chain ending at `id` without the commits past the kept limit.
+fn prune(repo: &Repository, id: Oid) -> Result<Oid> {
+    let mut chain = Vec::new();
+    let mut next = Some(repo.find_commit(id)?);
+    while let Some(commit) = next {
+        next = commit.parents().next();
+        chain.push(commit);
+    }
+    if chain.len() <= KEPT_DISCARDS {
+        return Ok(id);
+    }
+
+    let mut parent: Option<Commit> = None;
+    for commit in chain[..KEPT_DISCARDS].iter().rev() {
+        let id = repo.commit(
+            None,
+            &commit.author(),
+            &commit.committer(),
+            commit.message().unwrap_or_default(),
+            &commit.tree()?,
+            parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+        )?;
+        parent = Some(repo.find_commit(id)?);
+    }
+    Ok(parent.map_or(id, |commit| commit.id()))
+}
+
+fn workdir(repo: &Repository) -> Result<&Path> {
+    repo.workdir()
+        .context("Bare repositories have no work tree to discard from")
+}
+
+fn signature(repo: &Repository) -> Result<Signature<'static>> {
+    match repo.signature() {
+        Ok(signature) => Ok(signature.to_owned()),
+        Err(_) => Ok(Signature::now("gittop", "gittop@localhost")?),
+    }
+}
+
+fn index_entry(path: &Path, mode: u32, id: Oid) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.to_string_lossy().into_owned().into_bytes(),
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    if metadata.permissions().mode() & 0o111 != 0 {
+        EXECUTABLE_MODE
+    } else {
+        FILE_MODE
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> u32 {
+    FILE_MODE
+}
+
+#[cfg(unix)]
+fn write_file(path: &Path, content: &[u8], mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if mode == SYMLINK_MODE {
+        let target = String::from_utf8_lossy(content).into_owned();
+        std::os::unix::fs::symlink(target, path)?;
+        return Ok(());
+    }
+    fs::write(path, content)?;
+    let permissions = if mode == EXECUTABLE_MODE { 0o755 } else { 0o644 };
+    fs::set_permissions(path, fs::Permissions::from_mode(permissions))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_file(path: &Path, content: &[u8], _mode: u32) -> Result<()> {
+    fs::write(path, content)?;
+    Ok(())
+}
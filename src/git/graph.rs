@@ -0,0 +1,216 @@
+use git2::Oid;
+
+/// One character of the graph, with the lane whose colour it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphCell {
+    pub glyph: char,
+    pub lane: usize,
+}
+
+/// The graph next to one commit: the row with the commit itself, and the
+/// row below it that leads on to its parents.
+#[derive(Debug, Clone, Default)]
+pub struct GraphRow {
+    pub commit: Vec<GraphCell>,
+    pub edges: Vec<GraphCell>,
+}
+
+/// Lays commits out in lanes, one commit at a time, the way
+/// `git log --graph` does. Commits must come in topological order, children
+/// before their parents. Each lane is two cells wide: the line itself and
+/// the gap to its right, which carries horizontal edges.
+#[derive(Debug, Clone, Default)]
+pub struct GraphBuilder {
+    // The commit each lane is heading down to, if it's in use.
+    lanes: Vec<Option<Oid>>,
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, id: Oid, parents: &[Oid]) -> GraphRow {
+        let column = match self.lanes.iter().position(|lane| *lane == Some(id)) {
+            Some(column) => column,
+            None => self.free_lane(),
+        };
+
+        // Other lanes that were heading to this commit end here. Nearer lanes
+        // are drawn first so that edges to farther ones pass over them.
+        let mut merging: Vec<usize> = (0..self.lanes.len())
+            .filter(|&i| i != column && self.lanes[i] == Some(id))
+            .collect();
+        merging.sort_by_key(|&lane| lane.abs_diff(column));
+        let mut commit = self.vertical_cells();
+        commit[column * 2] = cell('●', column);
+        for &lane in &merging {
+            let glyph = if lane > column { '╯' } else { '╰' };
+            Self::draw_edge(&mut commit, column, lane, glyph);
+            self.lanes[lane] = None;
+        }
+
+        self.lanes[column] = parents.first().copied();
+        let mut forks = Vec::new();
+        for &parent in parents.iter().skip(1) {
+            match self.lanes.iter().position(|lane| *lane == Some(parent)) {
+                Some(lane) => forks.push((lane, if lane > column { '┤' } else { '├' })),
+                None => {
+                    let lane = self.free_lane();
+                    self.lanes[lane] = Some(parent);
+                    forks.push((lane, if lane > column { '╮' } else { '╭' }));
+                }
+            }
+        }
+
+        forks.sort_by_key(|&(lane, _)| lane.abs_diff(column));
+        let mut edges = self.vertical_cells();
+        for &(lane, glyph) in &forks {
+            Self::draw_edge(&mut edges, column, lane, glyph);
+        }
+        if !forks.is_empty() {
+            let right = forks.iter().any(|&(lane, _)| lane > column);
+            let left = forks.iter().any(|&(lane, _)| lane < column);
+            edges[column * 2] = cell(
+                match (left, right) {
+                    (true, true) => '┼',
+                    (true, false) => '┤',
+                    _ => '├',
+                },
+                column,
+            );
+        }
+
+        while self.lanes.last() == Some(&None) {
+            self.lanes.pop();
+        }
+        GraphRow {
+            commit: trim(commit),
+            edges: trim(edges),
+        }
+    }
+
+    fn free_lane(&mut self) -> usize {
+        match self.lanes.iter().position(Option::is_none) {
+            Some(lane) => lane,
+            None => {
+                self.lanes.push(None);
+                self.lanes.len() - 1
+            }
+        }
+    }
+
+    // A `│` for every lane in use, with room for any lane added since.
+    fn vertical_cells(&self) -> Vec<GraphCell> {
+        let mut cells = vec![cell(' ', 0); (self.lanes.len() + 1) * 2];
+        for (i, lane) in self.lanes.iter().enumerate() {
+            if lane.is_some() {
+                cells[i * 2] = cell('│', i);
+            }
+        }
+        cells
+    }
+
+    // A horizontal line from `from` to `to`, crossing any lanes in between,
+    // ending in `glyph` at `to`.
+    fn draw_edge(cells: &mut Vec<GraphCell>, from: usize, to: usize, glyph: char) {
+        if cells.len() < (to + 1) * 2 {
+            cells.resize((to + 1) * 2, cell(' ', 0));
+        }
+        let (start, end) = if from < to { (from * 2 + 1, to * 2) } else { (to * 2 + 1, from * 2) };
+        for cell in &mut cells[start..end] {
+            cell.glyph = match cell.glyph {
+                '│' | '┤' | '├' => '┼',
+                '╯' | '╰' => '┴',
+                '╮' | '╭' => '┬',
+                _ => {
+                    cell.lane = to;
+                    '─'
+                }
+            };
+        }
+        cells[to * 2] = cell(glyph, to);
+    }
+}
+
+fn cell(glyph: char, lane: usize) -> GraphCell {
+    GraphCell { glyph, lane }
+}
+
+fn trim(mut cells: Vec<GraphCell>) -> Vec<GraphCell> {
+    while cells.last().is_some_and(|cell| cell.glyph == ' ') {
+        cells.pop();
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u8) -> Oid {
+        Oid::from_bytes(&[n; 20]).unwrap()
+    }
+
+    // Each commit's two rows as text, for commits given as (id, parents).
+    fn draw(commits: &[(u8, &[u8])]) -> Vec<String> {
+        let mut graph = GraphBuilder::new();
+        let text = |cells: &[GraphCell]| cells.iter().map(|cell| cell.glyph).collect::<String>();
+        commits
+            .iter()
+            .flat_map(|&(commit, parents)| {
+                let parents: Vec<Oid> = parents.iter().map(|&parent| id(parent)).collect();
+                let row = graph.push(id(commit), &parents);
+                [text(&row.commit), text(&row.edges)]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn linear_history_stays_in_one_lane() {
+        assert_eq!(draw(&[(3, &[2]), (2, &[1]), (1, &[])]), ["●", "│", "●", "│", "●", ""]);
+    }
+
+    #[test]
+    fn octopus_merge_forks_a_lane_per_parent_and_joins_them_again() {
+        let rows = draw(&[
+            (5, &[2, 3, 4]),
+            (4, &[1]),
+            (3, &[1]),
+            (2, &[1]),
+            (1, &[]),
+        ]);
+        assert_eq!(
+            rows,
+            [
+                "●", "├─┬─╮",
+                "│ │ ●", "│ │ │",
+                "│ ● │", "│ │ │",
+                "● │ │", "│ │ │",
+                "●─┴─╯", "",
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_parent_already_in_a_lane_joins_that_lane() {
+        // 4 merges 3, which the first lane is already heading down to.
+        let rows = draw(&[(5, &[3]), (4, &[2, 3]), (3, &[1]), (2, &[1]), (1, &[])]);
+        assert_eq!(rows[2..4], ["│ ●", "├─┤"]);
+    }
+
+    #[test]
+    fn lanes_freed_by_a_root_are_reused() {
+        let mut graph = GraphBuilder::new();
+        graph.push(id(6), &[id(5)]);
+        graph.push(id(4), &[id(3)]);
+        let root = graph.push(id(5), &[]);
+        assert_eq!(root.commit[0].glyph, '●');
+
+        // The first lane is free again, so an unrelated branch starts there
+        // rather than in a third lane.
+        let row = graph.push(id(2), &[id(1)]);
+        assert_eq!(row.commit[0], cell('●', 0));
+        assert_eq!(graph.lanes.len(), 2);
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use anyhow::{Context, Result};
+use git2::{Oid, Revwalk};
+
+use crate::git::graph::GraphBuilder;
+use crate::git::repository::{GitRepository, HistoryScope};
+use crate::git::search::CommitFilter;
+use crate::git::status::CommitInfo;
+
+/// How many commits the total is counted in between serving pages.
+const COUNT_CHUNK: usize = 10_000;
+/// How many commits a page looks at before reporting back.
+const SCAN_CHUNK: usize = 2_000;
+/// How far the walk reads ahead of the commit it returns, so that a child
+/// committed on a clock behind its parent's still comes first.
+const LOOKAHEAD: usize = 100;
+
+/// Puts the commits of a walk by date in topological order, as the graph
+/// needs them: a commit is held back while a child read ahead of it is still
+/// to come. libgit2 can sort topologically itself, but only after reading
+/// the whole history.
+struct TopoOrder<'repo> {
+    repo: &'repo GitRepository,
+    revwalk: Revwalk<'repo>,
+    buffer: VecDeque<(Oid, CommitInfo)>,
+    // How many of the commits in the buffer each commit is a parent of.
+    pending: HashMap<Oid, usize>,
+    exhausted: bool,
+}
+
+impl<'repo> TopoOrder<'repo> {
+    fn new(repo: &'repo GitRepository, revwalk: Revwalk<'repo>) -> Self {
+        Self {
+            repo,
+            revwalk,
+            buffer: VecDeque::new(),
+            pending: HashMap::new(),
+            exhausted: false,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(Oid, CommitInfo)>> {
+        while !self.exhausted && self.buffer.len() < LOOKAHEAD {
+            let Some(id) = self.revwalk.next() else {
+                self.exhausted = true;
+                break;
+            };
+            let id = id?;
+            let commit = self.repo.commit_info(id)?;
+            for parent in &commit.parents {
+                *self.pending.entry(*parent).or_default() += 1;
+            }
+            self.buffer.push_back((id, commit));
+        }
+
+        // History has no cycles, so some commit is always ready.
+        let ready = self
+            .buffer
+            .iter()
+            .position(|(id, _)| !self.pending.contains_key(id))
+            .unwrap_or(0);
+        let Some((id, commit)) = self.buffer.remove(ready) else {
+            return Ok(None);
+        };
+        for parent in &commit.parents {
+            if let Some(count) = self.pending.get_mut(parent) {
+                *count -= 1;
+                if *count == 0 {
+                    self.pending.remove(parent);
+                }
+            }
+        }
+        Ok(Some((id, commit)))
+    }
+}
+
+/// Walks the history a page at a time, keeping the graph lanes going from
+/// one page to the next. With a filter, every commit is still returned, so
+/// that the graph holds together, and the ones that match are pointed out.
+pub struct HistoryWalk<'repo> {
+    repo: &'repo GitRepository,
+    commits: TopoOrder<'repo>,
+    filter: Option<CommitFilter>,
+    labels: HashMap<Oid, Vec<String>>,
+    graph: GraphBuilder,
+    returned: usize,
+    matched: usize,
+    done: bool,
+}
+
+impl<'repo> HistoryWalk<'repo> {
+    pub fn new(
+        repo: &'repo GitRepository,
+        scope: HistoryScope,
+        filter: Option<CommitFilter>,
+    ) -> Result<Self> {
+        Ok(Self {
+            repo,
+            commits: TopoOrder::new(repo, repo.history_revwalk(scope)?),
+            filter,
+            labels: repo.ref_labels()?,
+            graph: GraphBuilder::new(),
+            returned: 0,
+            matched: 0,
+            done: false,
+        })
+    }
+
+    /// The next `count` commits, or more until `matches` of them match the
+    /// filter, but no more than `limit`; fewer if the history runs out.
+    /// Along with them, the positions of the matching ones.
+    pub fn next_page(
+        &mut self,
+        count: usize,
+        matches: usize,
+        limit: usize,
+    ) -> Result<(Vec<CommitInfo>, Vec<usize>)> {
+        let mut commits = Vec::new();
+        let mut matching = Vec::new();
+        while (commits.len() < count || matching.len() < matches) && commits.len() < limit {
+            let Some((id, mut commit)) = self.commits.next()? else {
+                self.done = true;
+                break;
+            };
+            if let Some(filter) = &self.filter {
+                if self.repo.commit_matches(id, filter)? {
+                    matching.push(commits.len());
+                }
+            }
+            commit.refs = self.labels.remove(&id).unwrap_or_default();
+            commit.graph = self.graph.push(id, &commit.parents);
+            commits.push(commit);
+        }
+        self.returned += commits.len();
+        self.matched += matching.len();
+        Ok((commits, matching))
+    }
+
+    /// How many commits the walk has returned so far.
+    pub fn returned(&self) -> usize {
+        self.returned
+    }
+
+    /// How many of them matched the filter.
+    pub fn matched(&self) -> usize {
+        self.matched
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// What the loader thread sends back, tagged with the generation of the
+/// walk it belongs to.
+pub enum HistoryEvent {
+    /// More commits, and the positions among them of those that match the
+    /// filter; `reset` says they replace what was loaded before, and
+    /// `complete` that the request they answer has been served in full.
+    Page {
+        generation: u64,
+        commits: Vec<CommitInfo>,
+        matches: Vec<usize>,
+        reset: bool,
+        complete: bool,
+        done: bool,
+    },
+    Total { generation: u64, count: usize },
+    Failed { generation: u64, error: String },
+}
+
+enum Request {
+    Reset {
+        generation: u64,
+        scope: HistoryScope,
+        filter: Option<CommitFilter>,
+        count: usize,
+    },
+    /// At least `count` more commits, and as many as it takes to find
+    /// `matches` more matches.
+    More {
+        generation: u64,
+        count: usize,
+        matches: usize,
+    },
+}
+
+/// Loads the commit history on a thread of its own, which owns its
+/// `Repository` and the `Revwalk` so that each page carries on where the
+/// last one stopped. Pages are served a chunk of commits at a time, so the
+/// search for a filter that matches rarely streams in and a new request
+/// never waits long. The total number of commits is counted in the
+/// background while the thread is otherwise idle, once for each set of tips
+/// the history starts from.
+pub struct HistoryLoader {
+    requests: Sender<Request>,
+    events: Receiver<HistoryEvent>,
+    generation: u64,
+    loading: bool,
+    done: bool,
+    total: Option<usize>,
+}
+
+impl HistoryLoader {
+    pub fn new(repo_path: &Path) -> Result<Self> {
+        let repo = GitRepository::open(repo_path)?;
+        let (requests, request_rx) = channel();
+        let (event_tx, events) = channel();
+        thread::Builder::new()
+            .name("gittop-history".to_string())
+            .spawn(move || run(repo, request_rx, event_tx))
+            .context("Failed to start history loader")?;
+
+        Ok(Self {
+            requests,
+            events,
+            generation: 0,
+            loading: false,
+            done: false,
+            total: None,
+        })
+    }
+
+    /// Starts the walk over, loading its first `count` commits and marking
+    /// those that match `filter`.
+    pub fn reset(&mut self, scope: HistoryScope, filter: Option<CommitFilter>, count: usize) {
+        self.generation += 1;
+        self.loading = true;
+        self.done = false;
+        self.total = None;
+        let _ = self.requests.send(Request::Reset {
+            generation: self.generation,
+            scope,
+            filter,
+            count,
+        });
+    }
+
+    /// Asks for `count` more commits, unless a page is already on its way or
+    /// the walk is finished.
+    pub fn load_more(&mut self, count: usize) {
+        if self.loading || self.done {
+            return;
+        }
+        self.loading = true;
+        let _ = self.requests.send(Request::More {
+            generation: self.generation,
+            count,
+            matches: 0,
+        });
+    }
+
+    /// Loads commits until another one matches the filter, on top of any
+    /// page already on its way.
+    pub fn find_match(&mut self) {
+        if self.done {
+            return;
+        }
+        self.loading = true;
+        let _ = self.requests.send(Request::More {
+            generation: self.generation,
+            count: 0,
+            matches: 1,
+        });
+    }
+
+    /// The next event from the current walk; stale ones are dropped.
+    pub fn try_recv(&mut self) -> Option<HistoryEvent> {
+        while let Ok(event) = self.events.try_recv() {
+            match &event {
+                HistoryEvent::Page {
+                    generation,
+                    complete,
+                    done,
+                    ..
+                } if *generation == self.generation => {
+                    self.loading = !complete && !done;
+                    self.done = *done;
+                }
+                HistoryEvent::Total { generation, count } if *generation == self.generation => {
+                    self.total = Some(*count);
+                }
+                HistoryEvent::Failed { generation, .. } if *generation == self.generation => {
+                    self.loading = false;
+                    self.done = true;
+                }
+                _ => continue,
+            }
+            return Some(event);
+        }
+        None
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// The number of commits in the walk, once it has been counted.
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+}
+
+// A count of the commits reachable from `tips`, finished or under way.
+struct Count<'repo> {
+    generation: u64,
+    tips: Vec<Oid>,
+    revwalk: Revwalk<'repo>,
+    counted: usize,
+}
+
+fn run(repo: GitRepository, requests: Receiver<Request>, events: Sender<HistoryEvent>) {
+    let mut walk: Option<(u64, HistoryWalk)> = None;
+    let mut counter: Option<Count> = None;
+    // The tips last counted from and the count; refreshes mostly leave the
+    // history as it was.
+    let mut last_count: Option<(Vec<Oid>, usize)> = None;
+    // Commits and matches still owed to the current request, and whether the
+    // next page replaces the list.
+    let mut wanted = 0;
+    let mut wanted_matches = 0;
+    let mut reset = false;
+    // The pages of a new walk, held back until the request is served so that
+    // the list is replaced in one go and the selection can be found in it.
+    let mut held: (Vec<CommitInfo>, Vec<usize>) = Default::default();
+    loop {
+        // Block for requests, unless there is work left to do.
+        let request = if wanted > 0 || wanted_matches > 0 || counter.is_some() {
+            match requests.try_recv() {
+                Ok(request) => Some(request),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        } else {
+            match requests.recv() {
+                Ok(request) => Some(request),
+                Err(_) => return,
+            }
+        };
+
+        match request {
+            Some(Request::Reset {
+                generation,
+                scope,
+                filter,
+                count,
+            }) => {
+                walk = None;
+                counter = None;
+                wanted = 0;
+                wanted_matches = 0;
+                held = Default::default();
+                let started = HistoryWalk::new(&repo, scope, filter).and_then(|new_walk| {
+                    let tips = repo.history_tips(scope)?;
+                    match &last_count {
+                        Some((counted_tips, count)) if *counted_tips == tips => {
+                            let _ = events.send(HistoryEvent::Total {
+                                generation,
+                                count: *count,
+                            });
+                        }
+                        _ => {
+                            counter = Some(Count {
+                                generation,
+                                revwalk: repo.history_revwalk(scope)?,
+                                tips,
+                                counted: 0,
+                            });
+                        }
+                    }
+                    Ok(new_walk)
+                });
+                match started {
+                    Ok(new_walk) => {
+                        walk = Some((generation, new_walk));
+                        wanted = count;
+                        reset = true;
+                    }
+                    Err(e) => {
+                        let _ = events.send(HistoryEvent::Failed {
+                            generation,
+                            error: e.to_string(),
+                        });
+                    }
+                }
+                continue;
+            }
+            Some(Request::More {
+                generation,
+                count,
+                matches,
+            }) => {
+                if walk.as_ref().is_some_and(|(current, _)| *current == generation) {
+                    wanted = wanted.max(count);
+                    wanted_matches = wanted_matches.max(matches);
+                }
+                continue;
+            }
+            None => {}
+        }
+
+        if let (true, Some((generation, walk))) = (wanted > 0 || wanted_matches > 0, &mut walk) {
+            let event = match walk.next_page(wanted, wanted_matches, SCAN_CHUNK) {
+                Ok((mut commits, mut matches)) => {
+                    wanted = wanted.saturating_sub(commits.len());
+                    wanted_matches = wanted_matches.saturating_sub(matches.len());
+                    let done = walk.is_done();
+                    let complete = wanted == 0 && wanted_matches == 0;
+                    if reset {
+                        let start = held.0.len();
+                        held.0.append(&mut commits);
+                        held.1.extend(matches.iter().map(|i| start + i));
+                        if !complete && !done {
+                            continue;
+                        }
+                        (commits, matches) = std::mem::take(&mut held);
+                    }
+                    if done {
+                        wanted = 0;
+                        wanted_matches = 0;
+                        if let Some(count) = counter.take() {
+                            last_count = Some((count.tips, walk.returned()));
+                        }
+                        let _ = events.send(HistoryEvent::Total {
+                            generation: *generation,
+                            count: walk.returned(),
+                        });
+                    }
+                    HistoryEvent::Page {
+                        generation: *generation,
+                        commits,
+                        matches,
+                        reset: std::mem::take(&mut reset),
+                        complete,
+                        done,
+                    }
+                }
+                Err(e) => {
+                    wanted = 0;
+                    wanted_matches = 0;
+                    HistoryEvent::Failed {
+                        generation: *generation,
+                        error: e.to_string(),
+                    }
+                }
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        } else if let Some(count) = &mut counter {
+            let chunk = count.revwalk.by_ref().take(COUNT_CHUNK).count();
+            count.counted += chunk;
+            if chunk < COUNT_CHUNK {
+                let _ = events.send(HistoryEvent::Total {
+                    generation: count.generation,
+                    count: count.counted,
+                });
+                if let Some(count) = counter.take() {
+                    last_count = Some((count.tips, count.counted));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Repository, Signature, Time};
+
+    #[test]
+    fn children_come_before_parents_despite_clock_skew() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let commit = |message: &str, seconds: i64, parents: &[Oid]| {
+            let signature = Signature::new("Jane Doe", "jane@example.com", &Time::new(seconds, 0)).unwrap();
+            let parents: Vec<_> = parents.iter().map(|id| repo.find_commit(*id).unwrap()).collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(None, &signature, &signature, message, &tree, &parents).unwrap()
+        };
+        // `skewed` was made on a clock behind that of its parent `root`, so a
+        // walk by date reaches `root` through `side` first.
+        let root = commit("root", 100, &[]);
+        let side = commit("side", 300, &[root]);
+        let skewed = commit("skewed", 50, &[root]);
+        let merge = commit("merge", 400, &[side, skewed]);
+        repo.reference("refs/heads/main", merge, true, "").unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+
+        let repo = GitRepository::open(dir.path()).unwrap();
+        let mut walk = HistoryWalk::new(&repo, HistoryScope::Head, None).unwrap();
+        let (commits, _) = walk.next_page(10, 0, 10).unwrap();
+        let messages: Vec<&str> = commits.iter().map(|commit| commit.message.as_str()).collect();
+        assert_eq!(messages, ["merge", "side", "skewed", "root"]);
+        assert!(walk.is_done());
+        assert_eq!(commits[3].graph.commit.iter().filter(|cell| cell.glyph == '●').count(), 1);
+    }
+
+    #[test]
+    fn all_refs_start_from_the_newest_tip() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let signature = |seconds| Signature::new("Jane Doe", "jane@example.com", &Time::new(seconds, 0)).unwrap();
+        let old = repo.commit(None, &signature(100), &signature(100), "old", &tree, &[]).unwrap();
+        let old_commit = repo.find_commit(old).unwrap();
+        let new = repo
+            .commit(None, &signature(200), &signature(200), "new", &tree, &[&old_commit])
+            .unwrap();
+        // `a-old` sorts first by name but is an ancestor of `b-new`.
+        repo.reference("refs/heads/a-old", old, true, "").unwrap();
+        repo.reference("refs/heads/b-new", new, true, "").unwrap();
+        repo.set_head("refs/heads/a-old").unwrap();
+
+        let repo = GitRepository::open(dir.path()).unwrap();
+        let mut walk = HistoryWalk::new(&repo, HistoryScope::AllRefs, None).unwrap();
+        let (commits, _) = walk.next_page(10, 0, 10).unwrap();
+        let messages: Vec<&str> = commits.iter().map(|commit| commit.message.as_str()).collect();
+        assert_eq!(messages, ["new", "old"]);
+    }
+
+    #[test]
+    fn a_filter_marks_matches_and_keeps_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let mut parents = Vec::new();
+        for (seconds, message) in [(100, "Add parser"), (200, "Fix typo"), (300, "Fix parser")] {
+            let signature = Signature::new("Jane Doe", "jane@example.com", &Time::new(seconds, 0)).unwrap();
+            let parent: Vec<_> = parents.iter().collect();
+            let id = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parent).unwrap();
+            parents = vec![repo.find_commit(id).unwrap()];
+        }
+
+        let repo = GitRepository::open(dir.path()).unwrap();
+        let filter = CommitFilter::parse("fix").unwrap();
+        let mut walk = HistoryWalk::new(&repo, HistoryScope::Head, Some(filter)).unwrap();
+        // Asked for one commit and one match, the page stops at the first.
+        let (commits, matches) = walk.next_page(1, 1, 10).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(matches, [0]);
+        // Asked for two more matches where there is one, it walks to the end.
+        let (commits, matches) = walk.next_page(0, 2, 10).unwrap();
+        let messages: Vec<&str> = commits.iter().map(|commit| commit.message.as_str()).collect();
+        assert_eq!(messages, ["Fix typo", "Add parser"]);
+        assert_eq!(matches, [0]);
+        assert!(walk.is_done());
+        assert_eq!((walk.returned(), walk.matched()), (3, 2));
+    }
+}
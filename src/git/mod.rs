@@ -1,5 +1,32 @@
+pub mod branches;
+pub mod commit;
+pub mod detail;
+pub mod diff;
+pub mod discard;
+pub mod graph;
+pub mod history;
+pub mod operation;
 pub mod repository;
+pub mod search;
+pub mod snapshot;
+pub mod stash;
 pub mod status;
+pub mod tags;
+pub mod worker;
 
-pub use repository::GitRepository;
-pub use status::{FileStatus, CommitInfo, RemoteStatus};
\ No newline at end of file
+pub use branches::BranchInfo;
+pub use detail::{DetailEvent, DetailLoader};
+pub use diff::{DiffSource, FileDiff};
+pub use discard::DiscardRecord;
+pub use graph::{GraphCell, GraphRow};
+pub use history::{HistoryEvent, HistoryLoader};
+pub use operation::OperationState;
+pub use repository::{GitRepository, HistoryScope, RenameDetection};
+pub use search::CommitFilter;
+pub use snapshot::RepoSnapshot;
+pub use stash::StashInfo;
+pub use status::{
+    CommitDetail, CommitInfo, FileStatus, LineStats, Person, RemoteStatus, Rename, StatusEntry,
+};
+pub use tags::{TagInfo, TagOrder};
+pub use worker::StatusWorker;
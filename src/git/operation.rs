@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository, RepositoryState};
+
+/// A merge, rebase, cherry-pick, revert or bisect in progress, read from the
+/// state files git leaves in the git directory while one is under way.
+#[derive(Debug, Clone)]
+pub struct OperationState {
+    pub state: RepositoryState,
+    /// The step being worked on and how many there are, counting from 1.
+    pub progress: Option<(usize, usize)>,
+    /// The branch being rebased, and the commit it's being rebased onto.
+    pub rebase_branch: Option<String>,
+    pub onto: Option<String>,
+    /// Steps still to come, as in the todo list: `pick 1a2b3c4 Summary`.
+    pub todo: Vec<String>,
+    /// The commits being merged, cherry-picked or reverted, as
+    /// `1a2b3c4d Summary`.
+    pub heads: Vec<String>,
+    /// The prepared commit message, from `MERGE_MSG`.
+    pub message: Option<String>,
+    /// The commits marked bad and good so far in a bisect.
+    pub bisect_bad: Option<String>,
+    pub bisect_good: Vec<String>,
+}
+
+impl OperationState {
+    /// What is going on, or `None` if nothing is.
+    pub fn read(repo: &Repository) -> Option<Self> {
+        let state = repo.state();
+        if state == RepositoryState::Clean {
+            return None;
+        }
+
+        let git_dir = repo.path();
+        let mut operation = Self {
+            state,
+            progress: None,
+            rebase_branch: None,
+            onto: None,
+            todo: Vec::new(),
+            heads: Vec::new(),
+            message: read_file(&git_dir.join("MERGE_MSG")),
+            bisect_bad: None,
+            bisect_good: Vec::new(),
+        };
+
+        match state {
+            RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => {
+                let dir = git_dir.join("rebase-merge");
+                operation.read_rebase(repo, &dir, "msgnum", "end");
+                operation.todo = todo_lines(&dir.join("git-rebase-todo"));
+            }
+            RepositoryState::Rebase
+            | RepositoryState::ApplyMailbox
+            | RepositoryState::ApplyMailboxOrRebase => {
+                operation.read_rebase(repo, &git_dir.join("rebase-apply"), "next", "last");
+            }
+            RepositoryState::Merge => {
+                operation.heads = oid_lines(&git_dir.join("MERGE_HEAD"))
+                    .into_iter()
+                    .map(|id| describe(repo, id))
+                    .collect();
+            }
+            RepositoryState::CherryPick
+            | RepositoryState::CherryPickSequence
+            | RepositoryState::Revert
+            | RepositoryState::RevertSequence => {
+                let head = match state {
+                    RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                        "CHERRY_PICK_HEAD"
+                    }
+                    _ => "REVERT_HEAD",
+                };
+                let ids = oid_lines(&git_dir.join(head));
+                operation.todo = todo_lines(&git_dir.join("sequencer/todo"));
+                // The todo list still starts with the commit being worked on.
+                let current = operation.todo.first().and_then(|step| step.split(' ').nth(1));
+                if let (Some(id), Some(current)) = (ids.first(), current) {
+                    if id.to_string().starts_with(current) {
+                        operation.todo.remove(0);
+                    }
+                }
+                operation.heads = ids.into_iter().map(|id| describe(repo, id)).collect();
+            }
+            _ => {}
+        }
+
+        // A bisect can be under way alongside the operations above.
+        if git_dir.join("BISECT_LOG").exists() {
+            operation.bisect_bad = repo
+                .refname_to_id("refs/bisect/bad")
+                .ok()
+                .map(|id| describe(repo, id));
+            if let Ok(refs) = repo.references_glob("refs/bisect/good-*") {
+                operation.bisect_good = refs
+                    .flatten()
+                    .filter_map(|reference| reference.target())
+                    .map(|id| describe(repo, id))
+                    .collect();
+            }
+        }
+
+        Some(operation)
+    }
+
+    /// A short name for the header, e.g. `REBASE-i 2/5`, the way git's
+    /// prompt script names it.
+    pub fn label(&self) -> String {
+        let name = match self.state {
+            RepositoryState::Clean => "",
+            RepositoryState::Merge => "MERGING",
+            RepositoryState::Revert | RepositoryState::RevertSequence => "REVERTING",
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "CHERRY-PICKING",
+            RepositoryState::Bisect => "BISECTING",
+            RepositoryState::Rebase => "REBASE",
+            RepositoryState::RebaseMerge => "REBASE-m",
+            RepositoryState::RebaseInteractive => "REBASE-i",
+            RepositoryState::ApplyMailbox => "AM",
+            RepositoryState::ApplyMailboxOrRebase => "AM/REBASE",
+        };
+        match self.progress {
+            Some((step, total)) => format!("{} {}/{}", name, step, total),
+            None => name.to_string(),
+        }
+    }
+
+    fn read_rebase(&mut self, repo: &Repository, dir: &Path, step: &str, total: &str) {
+        let number = |name: &str| read_file(&dir.join(name))?.trim().parse::<usize>().ok();
+        self.progress = number(step).zip(number(total));
+        self.rebase_branch = read_file(&dir.join("head-name")).map(|name| {
+            let name = name.trim();
+            name.strip_prefix("refs/heads/").unwrap_or(name).to_string()
+        });
+        self.onto = read_file(&dir.join("onto"))
+            .and_then(|onto| Oid::from_str(onto.trim()).ok())
+            .map(|id| describe(repo, id));
+    }
+}
+
+fn read_file(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+// The steps of a todo list, without comments and blank lines, and with
+// full commit ids shortened.
+fn todo_lines(path: &Path) -> Vec<String> {
+    read_file(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.splitn(3, ' ').collect::<Vec<_>>()[..] {
+            [command, id, rest] if id.len() == 40 && Oid::from_str(id).is_ok() => {
+                format!("{} {} {}", command, &id[..8], rest)
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+fn oid_lines(path: &Path) -> Vec<Oid> {
+    read_file(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| Oid::from_str(line.trim()).ok())
+        .collect()
+}
+
+fn describe(repo: &Repository, id: Oid) -> String {
+    match repo.find_commit(id) {
+        Ok(commit) => format!("{:.8} {}", id, commit.summary().unwrap_or_default()),
+        Err(_) => format!("{:.8}", id),
+    }
+}
@@ -1,13 +1,58 @@
 use anyhow::{Context, Result};
 use chrono::{Local, TimeZone};
-use git2::{Repository, StatusOptions, StatusShow};
+use git2::build::CheckoutBuilder;
+use git2::{
+    ApplyLocation, ApplyOptions, Delta, DiffDelta, DiffFindOptions, DiffFormat, DiffOptions,
+    IndexAddOption, Patch, Repository, Revwalk, StatusOptions, StatusShow,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::git::status::{CommitInfo, FileStatus, FileStatusType, RemoteStatus};
+use crate::git::branches::{self, BranchInfo};
+use crate::git::commit;
+use crate::git::diff::{DiffSource, FileDiff};
+use crate::git::discard::{self, DiscardRecord};
+use crate::git::graph::GraphRow;
+use crate::git::operation::OperationState;
+use crate::git::search::CommitFilter;
+use crate::git::stash::{self, StashInfo};
+use crate::git::tags::{self, TagInfo};
+use crate::git::status::{
+    CommitDetail, CommitInfo, DiffStats, FileStatus, FileStatusType, LineStats, Person,
+    RemoteStatus, Rename, StatusEntry,
+};
+
+/// How renames (and optionally copies) are paired up in the status lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenameDetection {
+    /// Minimum similarity, in percent, for a delete/add pair to be a rename.
+    pub threshold: u16,
+    pub copies: bool,
+}
+
+impl Default for RenameDetection {
+    fn default() -> Self {
+        Self {
+            threshold: 50,
+            copies: false,
+        }
+    }
+}
+
+/// Which commits the history walks: those reachable from HEAD, or from
+/// every branch, remote branch and tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HistoryScope {
+    #[default]
+    Head,
+    AllRefs,
+}
 
 pub struct GitRepository {
     repo: Repository,
     path: PathBuf,
+    rename_detection: RenameDetection,
 }
 
 impl GitRepository {
@@ -18,9 +63,15 @@ impl GitRepository {
         Ok(Self {
             path: path.to_path_buf(),
             repo,
+            rename_detection: RenameDetection::default(),
         })
     }
 
+    pub fn with_rename_detection(mut self, rename_detection: RenameDetection) -> Self {
+        self.rename_detection = rename_detection;
+        self
+    }
+
     pub fn current_branch(&self) -> Result<String> {
         let head = self.repo.head().context("Failed to get HEAD reference")?;
         
@@ -31,123 +82,632 @@ impl GitRepository {
         }
     }
 
+    /// The merge, rebase or similar operation under way, if any.
+    pub fn operation(&self) -> Option<OperationState> {
+        OperationState::read(&self.repo)
+    }
+
     pub fn staged_files(&self) -> Result<Vec<FileStatus>> {
-        let mut opts = StatusOptions::new();
-        opts.show(StatusShow::Index);
-        opts.include_untracked(false);
-        
-        self.get_status_files(opts, true)
+        Ok(self
+            .status_entries()?
+            .iter()
+            .filter_map(StatusEntry::staged)
+            .collect())
     }
 
     pub fn unstaged_files(&self) -> Result<Vec<FileStatus>> {
+        Ok(self
+            .status_entries()?
+            .iter()
+            .filter_map(StatusEntry::unstaged)
+            .collect())
+    }
+
+    pub fn status_entries(&self) -> Result<Vec<StatusEntry>> {
         let mut opts = StatusOptions::new();
-        opts.show(StatusShow::Workdir);
+        opts.show(StatusShow::IndexAndWorkdir);
         opts.include_untracked(true);
-        
-        self.get_status_files(opts, false)
-    }
+        opts.renames_head_to_index(true);
+        opts.renames_index_to_workdir(true);
+        opts.rename_threshold(self.rename_detection.threshold);
 
-    fn get_status_files(&self, mut opts: StatusOptions, staged: bool) -> Result<Vec<FileStatus>> {
         let statuses = self.repo.statuses(Some(&mut opts))?;
-        let mut files = Vec::new();
+        let copies = if self.rename_detection.copies {
+            self.staged_copies()?
+        } else {
+            HashMap::new()
+        };
+        let mut entries = Vec::new();
 
         for entry in statuses.iter() {
             let status = entry.status();
-            let path = entry.path().unwrap_or_default();
-            
-            let file_status = if staged {
-                self.get_staged_status(status)
+            let head_to_index = entry.head_to_index();
+            let index_to_workdir = entry.index_to_workdir();
+
+            // `StatusEntry::path` is the pre-rename path; list files under
+            // their current name.
+            let path = index_to_workdir
+                .as_ref()
+                .or(head_to_index.as_ref())
+                .and_then(|delta| delta.new_file().path())
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from(entry.path().unwrap_or_default()));
+
+            let (mut index, worktree) = if status.contains(git2::Status::CONFLICTED) {
+                (Some(FileStatusType::Conflicted), Some(FileStatusType::Conflicted))
             } else {
-                self.get_unstaged_status(status)
+                (Self::index_status(status), Self::worktree_status(status))
             };
 
-            if let Some(status_type) = file_status {
-                files.push(FileStatus {
-                    path: PathBuf::from(path),
-                    status: status_type,
-                    staged,
+            // Status doesn't carry the similarity of renames; the snapshot
+            // fills it in from the diffs behind its line counts.
+            let mut index_rename = head_to_index
+                .as_ref()
+                .filter(|_| status.contains(git2::Status::INDEX_RENAMED))
+                .and_then(|delta| Self::rename_from_delta(delta, None));
+            let worktree_rename = index_to_workdir
+                .as_ref()
+                .filter(|_| status.contains(git2::Status::WT_RENAMED))
+                .and_then(|delta| Self::rename_from_delta(delta, None));
+
+            if index == Some(FileStatusType::Added) {
+                if let Some(copy) = copies.get(&path) {
+                    index = Some(FileStatusType::Copied);
+                    index_rename = Some(copy.clone());
+                }
+            }
+
+            if index.is_some() || worktree.is_some() {
+                entries.push(StatusEntry {
+                    path,
+                    index,
+                    worktree,
+                    index_rename,
+                    worktree_rename,
                 });
             }
         }
 
-        Ok(files)
+        Ok(entries)
+    }
+
+    /// Per-file line counts of HEAD → index.
+    pub fn staged_line_stats(&self) -> Result<DiffStats> {
+        let head_tree = self.repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let mut diff = self.repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+        self.find_renames(&mut diff)?;
+        Self::line_stats(&diff)
+    }
+
+    /// Per-file line counts of index → work tree, untracked files included.
+    pub fn unstaged_line_stats(&self) -> Result<DiffStats> {
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let mut diff = self.repo.diff_index_to_workdir(None, Some(&mut opts))?;
+        self.find_renames(&mut diff)?;
+        Self::line_stats(&diff)
+    }
+
+    /// The diff of `paths` (a file, or an untracked directory) for `source`.
+    /// Renames pair up when both the old and the new path are given.
+    pub fn file_diff(&self, source: DiffSource, paths: &[&Path]) -> Result<Vec<FileDiff>> {
+        let mut opts = DiffOptions::new();
+        opts.disable_pathspec_match(true);
+        for path in paths {
+            opts.pathspec(path);
+        }
+
+        let mut diff = match source {
+            DiffSource::Staged => {
+                let head_tree = self.repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+                self.repo
+                    .diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))?
+            }
+            DiffSource::Unstaged => {
+                opts.include_untracked(true)
+                    .recurse_untracked_dirs(true)
+                    .show_untracked_content(true);
+                self.repo.diff_index_to_workdir(None, Some(&mut opts))?
+            }
+            DiffSource::Commit(id) => self.commit_diff(id, Some(&mut opts))?,
+            DiffSource::Stash(id) => stash::diff(&self.repo, id, Some(&mut opts))?,
+        };
+        self.find_renames(&mut diff)?;
+
+        FileDiff::from_diff(&diff, source)
+    }
+
+    /// Stages `paths` (files or directories), including deletions. Files are
+    /// added or removed by name; only directories are searched.
+    pub fn stage_paths(&self, paths: &[&Path]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let workdir = self.repo.workdir().context("Repository has no work tree")?;
+        let mut index = self.index()?;
+        for path in paths {
+            match fs::symlink_metadata(workdir.join(path)) {
+                Ok(metadata) if metadata.is_dir() => {
+                    // An escaped pathspec no longer matches as a directory
+                    // prefix, so match what is under it instead.
+                    let pathspec = [format!("{}/*", literal_pathspec(path).trim_end_matches('/'))];
+                    index.add_all(&pathspec, IndexAddOption::DEFAULT, None)?;
+                    index.update_all(&pathspec, None)?;
+                }
+                Ok(_) => index.add_path(path)?,
+                // A deleted file, or a directory of them.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    index.remove_path(path)?;
+                    index.remove_dir(path, 0)?;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to read {}", path.display()))
+                }
+            }
+        }
+        index.write().context("Failed to write the index")
+    }
+
+    pub fn stage_all(&self) -> Result<()> {
+        let mut index = self.index()?;
+        index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"], None)?;
+        index.write().context("Failed to write the index")
+    }
+
+    /// Resets the index entries of `paths` to HEAD, or removes them when
+    /// there is no commit yet.
+    pub fn unstage_paths(&self, paths: &[&Path]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let head = self.head_commit()?;
+        self.index()?;
+        let pathspecs = paths.iter().map(|path| literal_pathspec(path));
+        self.repo
+            .reset_default(head.as_ref().map(|commit| commit.as_object()), pathspecs)?;
+        Ok(())
+    }
+
+    pub fn unstage_all(&self) -> Result<()> {
+        let head = self.head_commit()?;
+        self.index()?;
+        self.repo
+            .reset_default(head.as_ref().map(|commit| commit.as_object()), ["*"])?;
+        Ok(())
+    }
+
+    /// Applies a patch built by `FileDiff::index_patch` to the index.
+    pub fn apply_to_index(&self, patch: &[u8]) -> Result<()> {
+        let diff = git2::Diff::from_buffer(patch)?;
+        self.index()?;
+        self.repo
+            .apply(&diff, ApplyLocation::Index, None)
+            .context("The patch no longer applies; refresh and try again")
+    }
+
+    /// Throws away the unstaged changes of `paths` (files or untracked
+    /// directories): tracked files are checked out from the index and
+    /// untracked ones deleted. Their content is saved as a discard first.
+    pub fn discard_paths(&self, paths: &[&Path]) -> Result<()> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .disable_pathspec_match(true);
+        for path in paths {
+            opts.pathspec(path);
+        }
+
+        let mut tracked = Vec::new();
+        let mut untracked = Vec::new();
+        for entry in self.repo.statuses(Some(&mut opts))?.iter() {
+            let path = PathBuf::from(entry.path().unwrap_or_default());
+            let status = entry.status();
+            if status.contains(git2::Status::CONFLICTED) {
+                anyhow::bail!("{} has conflicts; resolve them first", path.display());
+            }
+            if status.contains(git2::Status::WT_NEW) {
+                untracked.push(path);
+            } else if status.intersects(
+                git2::Status::WT_MODIFIED
+                    | git2::Status::WT_DELETED
+                    | git2::Status::WT_TYPECHANGE
+                    | git2::Status::WT_RENAMED,
+            ) {
+                tracked.push(path);
+            }
+        }
+
+        let all: Vec<PathBuf> = tracked.iter().chain(&untracked).cloned().collect();
+        discard::save(&self.repo, &all)?;
+
+        if !tracked.is_empty() {
+            let mut checkout = CheckoutBuilder::new();
+            checkout.force();
+            // git2 has no DISABLE_PATHSPEC_MATCH for checkouts; without the
+            // escaping, discarding `a?` would also discard `ab`, unsaved.
+            for path in &tracked {
+                checkout.path(literal_pathspec(path));
+            }
+            self.index()?;
+            self.repo.checkout_index(None, Some(&mut checkout))?;
+        }
+
+        let workdir = self.repo.workdir().context("Repository has no work tree")?;
+        for path in &untracked {
+            fs::remove_file(workdir.join(path))
+                .with_context(|| format!("Failed to delete {}", path.display()))?;
+            // Drop the directories the deletion emptied.
+            let mut dir = path.parent();
+            while let Some(parent) = dir.filter(|parent| !parent.as_os_str().is_empty()) {
+                if fs::remove_dir(workdir.join(parent)).is_err() {
+                    break;
+                }
+                dir = parent.parent();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies a patch built by `FileDiff::discard_patch` for `path` to the
+    /// work tree, saving the file as a discard first.
+    pub fn discard_lines(&self, path: &Path, patch: &[u8]) -> Result<()> {
+        let diff = git2::Diff::from_buffer(patch)?;
+        let mut check = ApplyOptions::new();
+        check.check(true);
+        self.repo
+            .apply(&diff, ApplyLocation::WorkDir, Some(&mut check))
+            .context("The patch no longer applies; refresh and try again")?;
+
+        discard::save(&self.repo, &[path.to_path_buf()])?;
+        self.repo.apply(&diff, ApplyLocation::WorkDir, None)?;
+        Ok(())
+    }
+
+    /// The most recent discards, newest first.
+    pub fn discards(&self) -> Result<Vec<DiscardRecord>> {
+        discard::list(&self.repo)
+    }
+
+    /// Writes a discard's files back into the work tree.
+    pub fn restore_discard(&self, id: git2::Oid) -> Result<Vec<PathBuf>> {
+        discard::restore(&self.repo, id)
+    }
+
+    /// Commits the index, or amends HEAD with it and `message`.
+    pub fn commit(&self, message: &str, amend: bool) -> Result<git2::Oid> {
+        commit::create(&self.repo, message, amend)
+    }
+
+    pub fn head_message(&self) -> Result<String> {
+        commit::head_message(&self.repo)
+    }
+
+    /// The repository's index, re-read if it changed on disk: libgit2 caches
+    /// it per repository and writes the cached copy back.
+    fn index(&self) -> Result<git2::Index> {
+        let mut index = self.repo.index()?;
+        index.read(false)?;
+        Ok(index)
+    }
+
+    fn head_commit(&self) -> Result<Option<git2::Commit<'_>>> {
+        match self.repo.head() {
+            Ok(head) => Ok(Some(head.peel_to_commit()?)),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn find_renames(&self, diff: &mut git2::Diff) -> Result<()> {
+        let mut find = DiffFindOptions::new();
+        find.renames(true)
+            .for_untracked(true)
+            .rename_threshold(self.rename_detection.threshold);
+        diff.find_similar(Some(&mut find))?;
+        Ok(())
+    }
+
+    fn line_stats(diff: &git2::Diff) -> Result<DiffStats> {
+        let mut stats = HashMap::new();
+
+        for idx in 0..diff.deltas().len() {
+            let Some(patch) = Patch::from_diff(diff, idx)? else {
+                continue;
+            };
+            let delta = patch.delta();
+            let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+                continue;
+            };
+
+            let binary = delta.flags().is_binary();
+            let (_, added, removed) = patch.line_stats()?;
+            stats.insert(
+                path.to_path_buf(),
+                LineStats {
+                    added,
+                    removed,
+                    binary,
+                },
+            );
+        }
+
+        Ok(DiffStats {
+            lines: stats,
+            similarity: Self::similarities(diff)?,
+        })
+    }
+
+    // The similarity libgit2 scored each rename and copy in `diff` with.
+    // git2 doesn't expose it on `DiffDelta`, but it's in the patch header
+    // as `similarity index 87%`.
+    fn similarities(diff: &git2::Diff) -> Result<HashMap<PathBuf, u8>> {
+        let mut similarities = HashMap::new();
+        diff.print(DiffFormat::PatchHeader, |delta, _, line| {
+            let header = String::from_utf8_lossy(line.content());
+            let similarity = header.lines().find_map(|line| {
+                line.strip_prefix("similarity index ")?
+                    .strip_suffix('%')?
+                    .parse::<u8>()
+                    .ok()
+            });
+            if let (Some(path), Some(similarity)) = (delta.new_file().path(), similarity) {
+                similarities.insert(path.to_path_buf(), similarity);
+            }
+            true
+        })?;
+        Ok(similarities)
+    }
+
+    // Status never reports copies, so diff HEAD against the index with copy
+    // detection and pick out the added files that turn out to be copies.
+    // Unmodified files are candidate sources too, which is why this is opt-in.
+    fn staged_copies(&self) -> Result<HashMap<PathBuf, Rename>> {
+        let head_tree = self.repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let mut diff_opts = DiffOptions::new();
+        diff_opts.include_unmodified(true);
+        let mut diff = self
+            .repo
+            .diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_opts))?;
+
+        let mut find = DiffFindOptions::new();
+        find.renames(true)
+            .copies(true)
+            .copies_from_unmodified(true)
+            .rename_threshold(self.rename_detection.threshold)
+            .copy_threshold(self.rename_detection.threshold);
+        diff.find_similar(Some(&mut find))?;
+        let similarities = Self::similarities(&diff)?;
+
+        Ok(diff
+            .deltas()
+            .filter(|delta| delta.status() == Delta::Copied)
+            .filter_map(|delta| {
+                let path = delta.new_file().path()?.to_path_buf();
+                let copy = Self::rename_from_delta(&delta, Some(&similarities))?;
+                Some((path, copy))
+            })
+            .collect())
     }
 
-    fn get_staged_status(&self, status: git2::Status) -> Option<FileStatusType> {
+    fn rename_from_delta(
+        delta: &DiffDelta,
+        similarities: Option<&HashMap<PathBuf, u8>>,
+    ) -> Option<Rename> {
+        let similarity = similarities
+            .zip(delta.new_file().path())
+            .and_then(|(similarities, path)| similarities.get(path).copied());
+        Some(Rename {
+            old_path: delta.old_file().path()?.to_path_buf(),
+            similarity,
+        })
+    }
+
+    fn index_status(status: git2::Status) -> Option<FileStatusType> {
         if status.contains(git2::Status::INDEX_NEW) {
             Some(FileStatusType::Added)
+        } else if status.contains(git2::Status::INDEX_RENAMED) {
+            Some(FileStatusType::Renamed)
         } else if status.contains(git2::Status::INDEX_MODIFIED) {
             Some(FileStatusType::Modified)
         } else if status.contains(git2::Status::INDEX_DELETED) {
             Some(FileStatusType::Deleted)
-        } else if status.contains(git2::Status::INDEX_RENAMED) {
-            Some(FileStatusType::Renamed)
+        } else if status.contains(git2::Status::INDEX_TYPECHANGE) {
+            Some(FileStatusType::TypeChanged)
         } else {
             None
         }
     }
 
-    fn get_unstaged_status(&self, status: git2::Status) -> Option<FileStatusType> {
+    fn worktree_status(status: git2::Status) -> Option<FileStatusType> {
         if status.contains(git2::Status::WT_NEW) {
             Some(FileStatusType::Untracked)
+        } else if status.contains(git2::Status::WT_RENAMED) {
+            Some(FileStatusType::Renamed)
         } else if status.contains(git2::Status::WT_MODIFIED) {
             Some(FileStatusType::Modified)
         } else if status.contains(git2::Status::WT_DELETED) {
             Some(FileStatusType::Deleted)
-        } else if status.contains(git2::Status::WT_RENAMED) {
-            Some(FileStatusType::Renamed)
-        } else if status.contains(git2::Status::CONFLICTED) {
-            Some(FileStatusType::Conflicted)
+        } else if status.contains(git2::Status::WT_TYPECHANGE) {
+            Some(FileStatusType::TypeChanged)
         } else {
             None
         }
     }
 
-    pub fn recent_commits(&self, count: usize) -> Result<Vec<CommitInfo>> {
+    /// A walk over the commits in `scope`, newest first. It is left unsorted
+    /// because libgit2 reads the whole history before returning the first
+    /// commit of a sorted walk; unsorted, it goes by commit date, starting
+    /// from the tips in the reverse of the order they were pushed in.
+    pub fn history_revwalk(&self, scope: HistoryScope) -> Result<Revwalk<'_>> {
         let mut revwalk = self.repo.revwalk()?;
-        revwalk.push_head()?;
-        
-        let mut commits = Vec::new();
-        
-        for (i, oid) in revwalk.enumerate() {
-            if i >= count {
-                break;
+        for tip in self.history_tips(scope)?.into_iter().rev() {
+            revwalk.push(tip)?;
+        }
+        Ok(revwalk)
+    }
+
+    /// The commits the history in `scope` starts from, newest first.
+    pub fn history_tips(&self, scope: HistoryScope) -> Result<Vec<git2::Oid>> {
+        let mut tips = Vec::new();
+        if let Ok(head) = self.repo.head() {
+            tips.push(head.peel_to_commit()?);
+        }
+        if scope == HistoryScope::AllRefs {
+            for glob in ["refs/heads/*", "refs/remotes/*", "refs/tags/*"] {
+                for reference in self.repo.references_glob(glob)? {
+                    // Tags of trees or blobs aren't part of the history.
+                    if let Ok(commit) = reference?.peel_to_commit() {
+                        tips.push(commit);
+                    }
+                }
             }
-            
-            let oid = oid?;
-            let commit = self.repo.find_commit(oid)?;
-            
-            let author = commit.author();
-            let timestamp = Local.timestamp_opt(commit.time().seconds(), 0)
-                .single()
-                .unwrap_or_else(Local::now);
-            
-            commits.push(CommitInfo {
-                hash: oid.to_string(),
-                short_hash: format!("{:.8}", oid),
-                author: author.name().unwrap_or("Unknown").to_string(),
-                message: commit.summary().unwrap_or("").to_string(),
-                timestamp,
-            });
         }
-        
-        Ok(commits)
+        tips.sort_by_key(|commit| std::cmp::Reverse(commit.time().seconds()));
+        let mut ids: Vec<git2::Oid> = tips.iter().map(|commit| commit.id()).collect();
+        let mut seen = HashSet::new();
+        ids.retain(|id| seen.insert(*id));
+        Ok(ids)
+    }
+
+    pub fn commit_matches(&self, id: git2::Oid, filter: &CommitFilter) -> Result<bool> {
+        filter.matches(&self.repo, &self.repo.find_commit(id)?)
     }
 
-    pub fn remote_status(&self) -> Result<RemoteStatus> {
-        let head = self.repo.head()?;
+    /// The summary of commit `id`, without decorations or graph.
+    pub fn commit_info(&self, id: git2::Oid) -> Result<CommitInfo> {
+        let commit = self.repo.find_commit(id)?;
+        let author = commit.author();
+        let timestamp = Local.timestamp_opt(commit.time().seconds(), 0)
+            .single()
+            .unwrap_or_else(Local::now);
+
+        Ok(CommitInfo {
+            hash: id.to_string(),
+            short_hash: format!("{:.8}", id),
+            author: author.name().unwrap_or("Unknown").to_string(),
+            message: commit.summary().unwrap_or("").to_string(),
+            timestamp,
+            parents: commit.parent_ids().collect(),
+            refs: Vec::new(),
+            graph: GraphRow::default(),
+        })
+    }
+
+    /// The commit `id` and the files it changed; `refs` are its labels, as
+    /// `ref_labels` gives them.
+    pub fn commit_detail(&self, id: git2::Oid, refs: Vec<String>) -> Result<CommitDetail> {
+        let commit = self.repo.find_commit(id)?;
+        let diff = self.commit_diff(id, None)?;
+        let stats = Self::line_stats(&diff)?;
+
+        let files = diff
+            .deltas()
+            .filter_map(|delta| {
+                let path = delta.new_file().path().or(delta.old_file().path())?;
+                let status = match delta.status() {
+                    Delta::Added => FileStatusType::Added,
+                    Delta::Deleted => FileStatusType::Deleted,
+                    Delta::Renamed => FileStatusType::Renamed,
+                    Delta::Copied => FileStatusType::Copied,
+                    Delta::Typechange => FileStatusType::TypeChanged,
+                    _ => FileStatusType::Modified,
+                };
+                let rename = matches!(delta.status(), Delta::Renamed | Delta::Copied)
+                    .then(|| Self::rename_from_delta(&delta, Some(&stats.similarity)))
+                    .flatten();
+                Some(FileStatus {
+                    path: path.to_path_buf(),
+                    status,
+                    staged: false,
+                    rename,
+                    stats: stats.lines.get(path).copied(),
+                })
+            })
+            .collect();
+
+        let author = Person::from_signature(&commit.author());
+        let committer = Person::from_signature(&commit.committer());
+        Ok(CommitDetail {
+            id,
+            author,
+            committer,
+            parents: commit.parent_ids().collect(),
+            refs,
+            message: commit.message().unwrap_or_default().to_string(),
+            files,
+        })
+    }
+
+    // First parent → commit, with renames paired up.
+    fn commit_diff(&self, id: git2::Oid, opts: Option<&mut DiffOptions>) -> Result<git2::Diff<'_>> {
+        let commit = self.repo.find_commit(id)?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let mut diff =
+            self.repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), opts)?;
+        self.find_renames(&mut diff)?;
+        Ok(diff)
+    }
+
+    /// Decorations for every commit a ref points at, HEAD first, the way
+    /// `git log --decorate` shows them. gittop's own refs and remote HEAD
+    /// symrefs are left out.
+    pub fn ref_labels(&self) -> Result<HashMap<git2::Oid, Vec<String>>> {
+        let mut labels: HashMap<git2::Oid, Vec<String>> = HashMap::new();
+        let head = self.repo.head().ok();
+        let head_branch = head.as_ref().filter(|head| head.is_branch()).and_then(|head| head.name());
+        if let Some(head) = &head {
+            if let Some(id) = head.target() {
+                let label = match head_branch.and_then(|_| head.shorthand()) {
+                    Some(branch) => format!("HEAD -> {}", branch),
+                    None => "HEAD".to_string(),
+                };
+                labels.entry(id).or_default().push(label);
+            }
+        }
+        for reference in self.repo.references()? {
+            let reference = reference?;
+            let name = reference.name().unwrap_or_default();
+            if name.starts_with("refs/gittop/") || name.ends_with("/HEAD") || Some(name) == head_branch {
+                continue;
+            }
+            if let Ok(commit) = reference.peel_to_commit() {
+                let prefix = if reference.is_tag() { "tag: " } else { "" };
+                labels
+                    .entry(commit.id())
+                    .or_default()
+                    .push(format!("{}{}", prefix, reference.shorthand().unwrap_or(name)));
+            }
+        }
+        Ok(labels)
+    }
+
+    /// How HEAD compares with its upstream branch; `None` before the first
+    /// commit.
+    pub fn remote_status(&self) -> Result<Option<RemoteStatus>> {
+        let head = match self.repo.head() {
+            Ok(head) => head,
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
         let local_branch = head.shorthand().unwrap_or("HEAD");
+        let untracked = || RemoteStatus {
+            remote_name: "origin".to_string(),
+            branch_name: local_branch.to_string(),
+            ahead: 0,
+            behind: 0,
+        };
         
         let upstream = match self.repo.branch_upstream_name(head.name().unwrap_or_default()) {
             Ok(name) => name,
-            Err(_) => {
-                return Ok(RemoteStatus {
-                    remote_name: "origin".to_string(),
-                    branch_name: local_branch.to_string(),
-                    ahead: 0,
-                    behind: 0,
-                });
-            }
+            Err(_) => return Ok(Some(untracked())),
         };
         
         let upstream_str = upstream.as_str().unwrap_or_default();
@@ -155,19 +715,200 @@ impl GitRepository {
         let remote_name = parts.get(2).unwrap_or(&"origin").to_string();
         
         let local_oid = head.target().context("Failed to get local HEAD")?;
-        let upstream_oid = self.repo.refname_to_id(upstream_str)?;
+        // An upstream branch that was deleted on the remote and pruned.
+        let upstream_oid = match self.repo.refname_to_id(upstream_str) {
+            Ok(id) => id,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(Some(untracked())),
+            Err(e) => return Err(e.into()),
+        };
         
         let (ahead, behind) = self.repo.graph_ahead_behind(local_oid, upstream_oid)?;
         
-        Ok(RemoteStatus {
+        Ok(Some(RemoteStatus {
             remote_name,
             branch_name: local_branch.to_string(),
             ahead,
             behind,
-        })
+        }))
+    }
+
+    /// Local branches with their tracking state, most recent first.
+    pub fn branches(&self) -> Result<Vec<BranchInfo>> {
+        branches::list(&self.repo)
+    }
+
+    pub fn checkout_branch(&self, name: &str) -> Result<()> {
+        branches::checkout(&self.repo, name)
+    }
+
+    pub fn create_branch(&self, name: &str, target: git2::Oid) -> Result<()> {
+        branches::create(&self.repo, name, target)
+    }
+
+    pub fn rename_branch(&self, name: &str, new_name: &str) -> Result<()> {
+        branches::rename(&self.repo, name, new_name)
+    }
+
+    pub fn delete_branch(&self, name: &str, force: bool) -> Result<()> {
+        branches::delete(&self.repo, name, force)
+    }
+
+    pub fn is_branch_merged(&self, name: &str) -> Result<bool> {
+        branches::is_merged(&self.repo, name)
+    }
+
+    /// Stashes the work tree and index changes, with untracked files too if
+    /// `include_untracked` is set.
+    pub fn stash_push(&mut self, message: Option<&str>, include_untracked: bool) -> Result<git2::Oid> {
+        let signature = self
+            .repo
+            .signature()
+            .context("No signature configured; set user.name and user.email")?;
+        let mut flags = git2::StashFlags::DEFAULT;
+        if include_untracked {
+            flags |= git2::StashFlags::INCLUDE_UNTRACKED;
+        }
+        Ok(self.repo.stash_save2(&signature, message, Some(flags))?)
+    }
+
+    pub fn tags(&self) -> Result<Vec<TagInfo>> {
+        tags::list(&self.repo)
+    }
+
+    /// Tags `target`, annotated if there is a message.
+    pub fn create_tag(&self, name: &str, target: git2::Oid, message: Option<&str>) -> Result<()> {
+        tags::create(&self.repo, name, target, message)
+    }
+
+    pub fn delete_tag(&self, name: &str) -> Result<()> {
+        tags::delete(&self.repo, name)
+    }
+
+    pub fn head_id(&self) -> Result<git2::Oid> {
+        Ok(self.repo.head()?.peel_to_commit()?.id())
+    }
+
+    pub fn stashes(&mut self) -> Result<Vec<StashInfo>> {
+        stash::list(&mut self.repo)
+    }
+
+    /// Applies a stash to the work tree; `pop` drops it once applied.
+    pub fn stash_apply(&mut self, id: git2::Oid, pop: bool) -> Result<()> {
+        stash::apply(&mut self.repo, id, pop)
+    }
+
+    pub fn stash_drop(&mut self, id: git2::Oid) -> Result<()> {
+        stash::drop(&mut self.repo, id)
     }
 
     pub fn path(&self) -> &Path {
         &self.path
     }
-}
\ No newline at end of file
+}
+
+// Index updates, `reset_default` and checkouts read pathspecs as globs and
+// git2 has no option not to, so escape them: otherwise unstaging `x?` would
+// unstage `xy` too.
+fn literal_pathspec(path: &Path) -> String {
+    let mut pathspec = String::new();
+    for c in path.to_string_lossy().chars() {
+        if matches!(c, '*' | '?' | '[' | '\\') {
+            pathspec.push('\\');
+        }
+        pathspec.push(c);
+    }
+    pathspec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A repository with `files` committed, and then changed to `changes`;
+    // `None` deletes a file.
+    fn repo_with(files: &[&str], changes: &[(&str, Option<&str>)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Jane Doe").unwrap();
+        config.set_str("user.email", "jane@example.com").unwrap();
+        for name in files {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "old\n").unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Add", &tree, &[]).unwrap();
+        for (name, content) in changes {
+            match content {
+                Some(content) => fs::write(dir.path().join(name), content).unwrap(),
+                None => fs::remove_file(dir.path().join(name)).unwrap(),
+            }
+        }
+        dir
+    }
+
+    fn staged(repo: &GitRepository) -> Vec<String> {
+        let mut paths: Vec<String> = repo
+            .staged_files()
+            .unwrap()
+            .iter()
+            .map(|file| file.path.to_string_lossy().into_owned())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn discarding_a_glob_like_name_leaves_its_siblings_alone() {
+        let dir = repo_with(&["a?", "ab"], &[("a?", Some("new\n")), ("ab", Some("new\n"))]);
+        let repo = GitRepository::open(dir.path()).unwrap();
+        repo.discard_paths(&[Path::new("a?")]).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("a?")).unwrap(), "old\n");
+        assert_eq!(fs::read_to_string(dir.path().join("ab")).unwrap(), "new\n");
+    }
+
+    #[test]
+    fn staging_files_takes_them_by_name() {
+        let dir = repo_with(
+            &["x?", "xy", "gone", "gone2"],
+            &[("x?", Some("new\n")), ("xy", Some("new\n")), ("gone", None), ("gone2", None)],
+        );
+        fs::write(dir.path().join("new"), "new\n").unwrap();
+        let repo = GitRepository::open(dir.path()).unwrap();
+        repo.stage_paths(&[Path::new("x?"), Path::new("gone"), Path::new("new")]).unwrap();
+        assert_eq!(staged(&repo), ["gone", "new", "x?"]);
+    }
+
+    #[test]
+    fn staging_a_directory_takes_its_changes_and_deletions() {
+        let dir = repo_with(
+            &["d?/a", "d?/b", "dx/a", "dx/b"],
+            &[("d?/a", Some("new\n")), ("d?/b", None), ("dx/a", Some("new\n")), ("dx/b", None)],
+        );
+        fs::write(dir.path().join("d?/c"), "new\n").unwrap();
+        let repo = GitRepository::open(dir.path()).unwrap();
+        repo.stage_paths(&[Path::new("d?")]).unwrap();
+        assert_eq!(staged(&repo), ["d?/a", "d?/b", "d?/c"]);
+
+        // A directory that is gone altogether.
+        fs::remove_dir_all(dir.path().join("dx")).unwrap();
+        repo.stage_paths(&[Path::new("dx")]).unwrap();
+        assert_eq!(staged(&repo), ["d?/a", "d?/b", "d?/c", "dx/a", "dx/b"]);
+    }
+
+    #[test]
+    fn staging_an_untracked_directory_takes_its_files() {
+        let dir = repo_with(&["a"], &[]);
+        fs::create_dir_all(dir.path().join("new/sub")).unwrap();
+        fs::write(dir.path().join("new/a"), "new\n").unwrap();
+        fs::write(dir.path().join("new/sub/b"), "new\n").unwrap();
+        let repo = GitRepository::open(dir.path()).unwrap();
+        repo.stage_paths(&[Path::new("new/")]).unwrap();
+        assert_eq!(staged(&repo), ["new/a", "new/sub/b"]);
+    }
+}
@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use git2::{Commit, Signature};
+use regex::{Regex, RegexBuilder};
+
+/// Narrows the history to the commits matching every part of a query.
+///
+/// Plain words match the message (case-insensitively); `re:PATTERN` matches
+/// it against a regex instead. `author:` and `committer:` match a name or
+/// email, `since:` and `until:` take `YYYY-MM-DD` dates, inclusive, and
+/// `path:` keeps the commits that changed a file or directory. Values with
+/// spaces can be quoted: `author:"Jane Doe"`.
+#[derive(Debug, Clone, Default)]
+pub struct CommitFilter {
+    query: String,
+    text: Option<String>,
+    pattern: Option<Regex>,
+    author: Option<String>,
+    committer: Option<String>,
+    since: Option<DateTime<Local>>,
+    until: Option<DateTime<Local>>,
+    path: Option<PathBuf>,
+}
+
+impl CommitFilter {
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut filter = Self {
+            query: query.trim().to_string(),
+            ..Self::default()
+        };
+        let mut words = Vec::new();
+
+        for token in tokens(query) {
+            let Some((key, value)) = token.split_once(':').filter(|(key, _)| is_key(key)) else {
+                words.push(token);
+                continue;
+            };
+            if value.is_empty() {
+                bail!("'{}:' needs a value", key);
+            }
+            match key {
+                "re" => {
+                    let pattern = RegexBuilder::new(value)
+                        .case_insensitive(true)
+                        .build()
+                        .with_context(|| format!("Invalid regex '{}'", value))?;
+                    filter.pattern = Some(pattern);
+                }
+                "author" => filter.author = Some(value.to_lowercase()),
+                "committer" => filter.committer = Some(value.to_lowercase()),
+                "since" => filter.since = Some(start_of_day(parse_date(value)?)),
+                "until" => {
+                    let next_day = parse_date(value)?.succ_opt().unwrap_or(NaiveDate::MAX);
+                    filter.until = Some(start_of_day(next_day));
+                }
+                "path" => filter.path = Some(PathBuf::from(value.trim_matches('/'))),
+                _ => unreachable!(),
+            }
+        }
+
+        if !words.is_empty() {
+            filter.text = Some(words.join(" ").to_lowercase());
+        }
+        Ok(filter)
+    }
+
+    /// The query as it was typed.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn matches(&self, repo: &git2::Repository, commit: &Commit) -> Result<bool> {
+        let message = commit.message().unwrap_or_default();
+        if let Some(text) = &self.text {
+            if !message.to_lowercase().contains(text.as_str()) {
+                return Ok(false);
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(message) {
+                return Ok(false);
+            }
+        }
+        if let Some(author) = &self.author {
+            if !person_matches(&commit.author(), author) {
+                return Ok(false);
+            }
+        }
+        if let Some(committer) = &self.committer {
+            if !person_matches(&commit.committer(), committer) {
+                return Ok(false);
+            }
+        }
+
+        let time = commit.time().seconds();
+        if self.since.is_some_and(|since| time < since.timestamp()) {
+            return Ok(false);
+        }
+        if self.until.is_some_and(|until| time >= until.timestamp()) {
+            return Ok(false);
+        }
+
+        match &self.path {
+            Some(path) => touches(repo, commit, path),
+            None => Ok(true),
+        }
+    }
+}
+
+fn is_key(key: &str) -> bool {
+    matches!(key, "re" | "author" | "committer" | "since" | "until" | "path")
+}
+
+// Splits on whitespace, keeping double-quoted runs together.
+fn tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
+
+fn person_matches(person: &Signature, needle: &str) -> bool {
+    [person.name(), person.email()]
+        .into_iter()
+        .flatten()
+        .any(|value| value.to_lowercase().contains(needle))
+}
+
+// Like `git log -- path`: the commit changed `path` relative to each of its
+// parents, so merges that took it unchanged from one side are left out.
+fn touches(repo: &git2::Repository, commit: &Commit, path: &std::path::Path) -> Result<bool> {
+    let entry_id = |commit: &Commit| -> Result<Option<git2::Oid>> {
+        if path.as_os_str().is_empty() {
+            return Ok(Some(commit.tree_id()));
+        }
+        match commit.tree()?.get_path(path) {
+            Ok(entry) => Ok(Some(entry.id())),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    };
+
+    let id = entry_id(commit)?;
+    if commit.parent_count() == 0 {
+        return Ok(id.is_some());
+    }
+    for parent in commit.parent_ids() {
+        if entry_id(&repo.find_commit(parent)?)? == id {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use git2::{Repository, Time};
+
+    #[test]
+    fn quotes_keep_values_with_spaces_together() {
+        assert_eq!(
+            tokens(r#"fix author:"Jane Doe"  path:src"#),
+            ["fix", "author:Jane Doe", "path:src"]
+        );
+
+        let filter = CommitFilter::parse(r#"author:"Jane Doe" "two  words""#).unwrap();
+        assert_eq!(filter.author.as_deref(), Some("jane doe"));
+        assert_eq!(filter.text.as_deref(), Some("two  words"));
+        assert_eq!(filter.query(), r#"author:"Jane Doe" "two  words""#);
+    }
+
+    #[test]
+    fn unknown_keys_are_searched_as_text() {
+        let filter = CommitFilter::parse("Fix: crash").unwrap();
+        assert_eq!(filter.text.as_deref(), Some("fix: crash"));
+        assert!(filter.author.is_none());
+    }
+
+    #[test]
+    fn empty_values_and_bad_dates_are_rejected() {
+        assert!(CommitFilter::parse("author:").is_err());
+        assert!(CommitFilter::parse("since:yesterday").is_err());
+        assert!(CommitFilter::parse("re:(").is_err());
+    }
+
+    #[test]
+    fn since_and_until_include_the_whole_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let commit_at = |local: &str| {
+            let time = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M:%S").unwrap();
+            let seconds = Local.from_local_datetime(&time).unwrap().timestamp();
+            let signature =
+                Signature::new("Jane Doe", "jane@example.com", &Time::new(seconds, 0)).unwrap();
+            let id = repo.commit(None, &signature, &signature, "Change", &tree, &[]).unwrap();
+            repo.find_commit(id).unwrap()
+        };
+
+        let filter = CommitFilter::parse("since:2024-03-05 until:2024-03-05").unwrap();
+        let matches = |local: &str| filter.matches(&repo, &commit_at(local)).unwrap();
+        assert!(!matches("2024-03-04 23:59:59"));
+        assert!(matches("2024-03-05 00:00:00"));
+        assert!(matches("2024-03-05 23:59:59"));
+        assert!(!matches("2024-03-06 00:00:00"));
+    }
+}
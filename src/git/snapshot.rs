@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use crate::git::repository::GitRepository;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::git::branches::BranchInfo;
+use crate::git::operation::OperationState;
+use crate::git::stash::StashInfo;
+use crate::git::tags::TagInfo;
+use crate::git::status::{DiffStats, FileStatus, LineStats, RemoteStatus, Rename, StatusEntry};
+
+/// Which parts of a `RepoSnapshot` a refresh recomputes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshScope {
+    pub status: bool,
+    /// The history isn't part of the snapshot; this reloads it separately.
+    pub commits: bool,
+    /// Branches, tags, the remote tracking status and the stash list.
+    pub refs: bool,
+}
+
+impl RefreshScope {
+    pub const NONE: Self = Self {
+        status: false,
+        commits: false,
+        refs: false,
+    };
+
+    pub const ALL: Self = Self {
+        status: true,
+        commits: true,
+        refs: true,
+    };
+
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            status: self.status || other.status,
+            commits: self.commits || other.commits,
+            refs: self.refs || other.refs,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RepoSnapshot {
+    pub branch: Option<String>,
+    pub operation: Option<OperationState>,
+    pub remote: Option<RemoteStatus>,
+    pub entries: Vec<StatusEntry>,
+    pub staged: Vec<FileStatus>,
+    pub unstaged: Vec<FileStatus>,
+    pub branches: Vec<BranchInfo>,
+    pub stashes: Vec<StashInfo>,
+    pub tags: Vec<TagInfo>,
+}
+
+impl RepoSnapshot {
+    /// Captures the parts of the snapshot covered by `scope` stage by stage,
+    /// giving up with `None` as soon as `is_cancelled` reports that a newer
+    /// refresh has superseded this one. Any part failing fails the whole
+    /// snapshot, rather than leave that part looking empty.
+    pub fn capture_until(
+        repo: &mut GitRepository,
+        scope: RefreshScope,
+        is_cancelled: impl Fn() -> bool,
+    ) -> Result<Option<Self>> {
+        let mut snapshot = Self {
+            // There is no branch to name before the first commit.
+            branch: repo.current_branch().ok(),
+            operation: repo.operation(),
+            ..Self::default()
+        };
+
+        if scope.status {
+            if is_cancelled() {
+                return Ok(None);
+            }
+            snapshot.entries = repo.status_entries().context("Failed to read the status")?;
+
+            if is_cancelled() {
+                return Ok(None);
+            }
+            let staged_stats = repo.staged_line_stats().context("Failed to diff the index")?;
+            let unstaged_stats = repo
+                .unstaged_line_stats()
+                .context("Failed to diff the work tree")?;
+            for entry in &mut snapshot.entries {
+                let path = &entry.path;
+                Self::attach_similarity(entry.index_rename.as_mut(), path, &staged_stats);
+                Self::attach_similarity(entry.worktree_rename.as_mut(), path, &unstaged_stats);
+            }
+            snapshot.staged = snapshot.entries.iter().filter_map(StatusEntry::staged).collect();
+            snapshot.unstaged = snapshot.entries.iter().filter_map(StatusEntry::unstaged).collect();
+            Self::attach_stats(&mut snapshot.staged, &staged_stats.lines);
+            Self::attach_stats(&mut snapshot.unstaged, &unstaged_stats.lines);
+        }
+
+        if scope.refs {
+            if is_cancelled() {
+                return Ok(None);
+            }
+            snapshot.remote = repo
+                .remote_status()
+                .context("Failed to compare with the upstream branch")?;
+            snapshot.branches = repo.branches().context("Failed to list branches")?;
+            snapshot.stashes = repo.stashes().context("Failed to list stashes")?;
+            snapshot.tags = repo.tags().context("Failed to list tags")?;
+        }
+
+        Ok(Some(snapshot))
+    }
+
+    // Status doesn't say how similar a rename is; the diff behind the line
+    // counts does. Copies come with theirs already.
+    fn attach_similarity(rename: Option<&mut Rename>, path: &Path, stats: &DiffStats) {
+        if let Some(rename) = rename.filter(|rename| rename.similarity.is_none()) {
+            rename.similarity = stats.similarity.get(path).copied();
+        }
+    }
+
+    // Untracked directories are listed as a single `dir/` entry; they get the
+    // sum of the files inside them.
+    fn attach_stats(files: &mut [FileStatus], stats: &HashMap<PathBuf, LineStats>) {
+        for file in files {
+            file.stats = stats.get(&file.path).copied().or_else(|| {
+                if !file.path.to_string_lossy().ends_with('/') {
+                    return None;
+                }
+                stats
+                    .iter()
+                    .filter(|(path, _)| path.starts_with(&file.path))
+                    .map(|(_, stats)| *stats)
+                    .reduce(|total, stats| total + stats)
+            });
+        }
+    }
+
+    pub fn staged_totals(&self) -> LineStats {
+        Self::totals(&self.staged)
+    }
+
+    pub fn unstaged_totals(&self) -> LineStats {
+        Self::totals(&self.unstaged)
+    }
+
+    fn totals(files: &[FileStatus]) -> LineStats {
+        files
+            .iter()
+            .filter_map(|file| file.stats)
+            .fold(LineStats::default(), |total, stats| total + stats)
+    }
+
+    /// Takes over the parts of `update` that were recomputed for `scope`.
+    pub fn merge(&mut self, update: RepoSnapshot, scope: RefreshScope) {
+        self.branch = update.branch;
+        self.operation = update.operation;
+        if scope.status {
+            self.entries = update.entries;
+            self.staged = update.staged;
+            self.unstaged = update.unstaged;
+        }
+        if scope.refs {
+            self.remote = update.remote;
+            self.branches = update.branches;
+            self.stashes = update.stashes;
+            self.tags = update.tags;
+        }
+    }
+
+    pub fn branch_name(&self) -> &str {
+        self.branch.as_deref().unwrap_or("Unknown")
+    }
+}
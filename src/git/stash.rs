@@ -0,0 +1,110 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, TimeZone};
+use git2::{Diff, DiffOptions, Oid, Repository, StashApplyOptions};
+
+/// One entry of `refs/stash`, newest first like `git stash list`.
+#[derive(Debug, Clone)]
+pub struct StashInfo {
+    pub index: usize,
+    pub id: Oid,
+    /// The branch the stash was made on, if it was made on one.
+    pub branch: Option<String>,
+    pub message: String,
+    pub time: DateTime<Local>,
+    /// The stash also holds untracked files.
+    pub untracked: bool,
+}
+
+pub fn list(repo: &mut Repository) -> Result<Vec<StashInfo>> {
+    let mut entries = Vec::new();
+    repo.stash_foreach(|index, message, &id| {
+        let (branch, message) = split_message(message);
+        entries.push((index, id, branch, message));
+        true
+    })?;
+
+    entries
+        .into_iter()
+        .map(|(index, id, branch, message)| {
+            let commit = repo.find_commit(id)?;
+            Ok(StashInfo {
+                index,
+                id,
+                branch,
+                message,
+                time: Local
+                    .timestamp_opt(commit.time().seconds(), 0)
+                    .single()
+                    .unwrap_or_else(Local::now),
+                untracked: commit.parent_count() > 2,
+            })
+        })
+        .collect()
+}
+
+// Stash messages read `WIP on main: 1a2b3c4 Summary` when made without a
+// message and `On main: message` with one.
+fn split_message(message: &str) -> (Option<String>, String) {
+    let rest = message
+        .strip_prefix("WIP on ")
+        .or_else(|| message.strip_prefix("On "));
+    match rest.and_then(|rest| rest.split_once(": ")) {
+        Some((branch, message)) => {
+            let branch = (branch != "(no branch)").then(|| branch.to_string());
+            (branch, message.to_string())
+        }
+        None => (None, message.to_string()),
+    }
+}
+
+/// What the stash changed relative to the commit it was made on, with any
+/// untracked files it saved shown as added.
+pub fn diff<'repo>(
+    repo: &'repo Repository,
+    id: Oid,
+    mut opts: Option<&mut DiffOptions>,
+) -> Result<Diff<'repo>> {
+    let stash = repo.find_commit(id)?;
+    let base = stash.parent(0)?.tree()?;
+    let mut diff = repo.diff_tree_to_tree(Some(&base), Some(&stash.tree()?), opts.as_deref_mut())?;
+    if let Ok(untracked) = stash.parent(2) {
+        let added = repo.diff_tree_to_tree(None, Some(&untracked.tree()?), opts)?;
+        diff.merge(&added)?;
+    }
+    Ok(diff)
+}
+
+/// Applies the stash `id` to the work tree, dropping it afterwards if `pop`
+/// is set and it applied cleanly.
+pub fn apply(repo: &mut Repository, id: Oid, pop: bool) -> Result<()> {
+    let index = find(repo, id)?;
+    let mut options = StashApplyOptions::new();
+    let result = if pop {
+        repo.stash_pop(index, Some(&mut options))
+    } else {
+        repo.stash_apply(index, Some(&mut options))
+    };
+    result.with_context(|| format!("Cannot apply stash@{{{}}}", index))
+}
+
+pub fn drop(repo: &mut Repository, id: Oid) -> Result<()> {
+    let index = find(repo, id)?;
+    repo.stash_drop(index)?;
+    Ok(())
+}
+
+// Stashes are addressed by position, which shifts whenever one is pushed or
+// dropped; look the position up again so that the right one is used.
+fn find(repo: &mut Repository, id: Oid) -> Result<usize> {
+    let mut found = None;
+    repo.stash_foreach(|index, _, &stash| {
+        if stash == id {
+            found = Some(index);
+        }
+        found.is_none()
+    })?;
+    match found {
+        Some(index) => Ok(index),
+        None => bail!("The stash {:.8} no longer exists", id),
+    }
+}
@@ -1,5 +1,7 @@
-use std::path::PathBuf;
-use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::git::graph::GraphRow;
+use chrono::{DateTime, FixedOffset, Local, TimeZone};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FileStatusType {
@@ -7,15 +9,139 @@ pub enum FileStatusType {
     Modified,
     Deleted,
     Renamed,
+    Copied,
+    TypeChanged,
     Untracked,
     Conflicted,
 }
 
+impl FileStatusType {
+    /// The letter `git status --short` uses for this state.
+    pub fn code(&self) -> char {
+        match self {
+            FileStatusType::Added => 'A',
+            FileStatusType::Modified => 'M',
+            FileStatusType::Deleted => 'D',
+            FileStatusType::Renamed => 'R',
+            FileStatusType::Copied => 'C',
+            FileStatusType::TypeChanged => 'T',
+            FileStatusType::Untracked => '?',
+            FileStatusType::Conflicted => 'U',
+        }
+    }
+}
+
+/// Where a renamed or copied file came from, with the similarity (0-100)
+/// libgit2 scored the old and new versions with when pairing them up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    pub old_path: PathBuf,
+    pub similarity: Option<u8>,
+}
+
+/// Lines added and removed in a file's diff; binary files have no counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineStats {
+    pub added: usize,
+    pub removed: usize,
+    pub binary: bool,
+}
+
+impl std::ops::Add for LineStats {
+    type Output = Self;
+
+    fn add(self, other: LineStats) -> Self {
+        Self {
+            added: self.added + other.added,
+            removed: self.removed + other.removed,
+            binary: self.binary && other.binary,
+        }
+    }
+}
+
+/// What a diff amounts to: line counts per file and, for renames and
+/// copies, their similarity; both keyed by the new path.
+#[derive(Debug, Clone, Default)]
+pub struct DiffStats {
+    pub lines: HashMap<PathBuf, LineStats>,
+    pub similarity: HashMap<PathBuf, u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileStatus {
     pub path: PathBuf,
     pub status: FileStatusType,
     pub staged: bool,
+    pub rename: Option<Rename>,
+    pub stats: Option<LineStats>,
+}
+
+impl FileStatus {
+    /// The path as shown in the status lists: `old → new (87%)` for renames
+    /// and copies.
+    pub fn display_path(&self) -> String {
+        display_path(&self.path, self.rename.as_ref())
+    }
+}
+
+pub fn display_path(path: &Path, rename: Option<&Rename>) -> String {
+    match rename {
+        Some(Rename {
+            old_path,
+            similarity: Some(similarity),
+        }) => format!(
+            "{} → {} ({}%)",
+            old_path.display(),
+            path.display(),
+            similarity
+        ),
+        Some(Rename { old_path, .. }) => format!("{} → {}", old_path.display(), path.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Index and work tree state of a single path, as in `git status --short`.
+#[derive(Debug, Clone)]
+pub struct StatusEntry {
+    pub path: PathBuf,
+    pub index: Option<FileStatusType>,
+    pub worktree: Option<FileStatusType>,
+    pub index_rename: Option<Rename>,
+    pub worktree_rename: Option<Rename>,
+}
+
+impl StatusEntry {
+    pub fn short_code(&self) -> String {
+        if self.worktree == Some(FileStatusType::Untracked) {
+            return "??".to_string();
+        }
+        let column = |status: &Option<FileStatusType>| status.as_ref().map_or(' ', FileStatusType::code);
+        format!("{}{}", column(&self.index), column(&self.worktree))
+    }
+
+    pub fn staged(&self) -> Option<FileStatus> {
+        self.index.as_ref().map(|status| FileStatus {
+            path: self.path.clone(),
+            status: status.clone(),
+            staged: true,
+            rename: self.index_rename.clone(),
+            stats: None,
+        })
+    }
+
+    pub fn unstaged(&self) -> Option<FileStatus> {
+        self.worktree.as_ref().map(|status| FileStatus {
+            path: self.path.clone(),
+            status: status.clone(),
+            staged: false,
+            rename: self.worktree_rename.clone(),
+            stats: None,
+        })
+    }
+
+    pub fn display_path(&self) -> String {
+        display_path(&self.path, self.index_rename.as_ref().or(self.worktree_rename.as_ref()))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +151,48 @@ pub struct CommitInfo {
     pub author: String,
     pub message: String,
     pub timestamp: DateTime<Local>,
+    pub parents: Vec<git2::Oid>,
+    /// Branches and tags pointing at the commit.
+    pub refs: Vec<String>,
+    pub graph: GraphRow,
+}
+
+/// An author or committer, with the time in their own timezone.
+#[derive(Debug, Clone)]
+pub struct Person {
+    pub name: String,
+    pub email: String,
+    pub time: DateTime<FixedOffset>,
+}
+
+impl Person {
+    pub fn from_signature(signature: &git2::Signature) -> Self {
+        let when = signature.when();
+        let offset = FixedOffset::east_opt(when.offset_minutes() * 60)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        Self {
+            name: signature.name().unwrap_or("Unknown").to_string(),
+            email: signature.email().unwrap_or_default().to_string(),
+            time: offset
+                .timestamp_opt(when.seconds(), 0)
+                .single()
+                .unwrap_or_else(|| Local::now().fixed_offset()),
+        }
+    }
+}
+
+/// Everything the commit detail view shows about one commit.
+#[derive(Debug, Clone)]
+pub struct CommitDetail {
+    pub id: git2::Oid,
+    pub author: Person,
+    pub committer: Person,
+    pub parents: Vec<git2::Oid>,
+    /// Short names of the refs pointing at the commit, HEAD first.
+    pub refs: Vec<String>,
+    pub message: String,
+    /// Changes against the first parent.
+    pub files: Vec<FileStatus>,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, TimeZone};
+use git2::{Oid, Reference, Repository, Sort};
+
+use crate::git::status::Person;
+
+/// A tag and the commit it points at.
+#[derive(Debug, Clone)]
+pub struct TagInfo {
+    pub name: String,
+    pub target: Oid,
+    pub target_summary: String,
+    pub annotated: bool,
+    /// Who made the tag and why; annotated tags only.
+    pub tagger: Option<Person>,
+    pub message: Option<String>,
+    /// When the tag was made, or for a lightweight tag when its commit was.
+    pub time: DateTime<Local>,
+    /// How many commits HEAD is past the tag, as counted by `git describe`;
+    /// `None` if the tag isn't in HEAD's history.
+    pub ahead: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagOrder {
+    /// Highest version first, comparing the numbers in names numerically.
+    #[default]
+    Version,
+    /// Newest first.
+    Date,
+}
+
+/// All tags that point (possibly through annotated tags) at a commit.
+pub fn list(repo: &Repository) -> Result<Vec<TagInfo>> {
+    let mut tags = Vec::new();
+
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let Ok(reference) = repo.find_reference(&format!("refs/tags/{}", name)) else {
+            continue;
+        };
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        let tag = reference.peel_to_tag().ok();
+        let tagger = tag
+            .as_ref()
+            .and_then(|tag| tag.tagger())
+            .map(|tagger| Person::from_signature(&tagger));
+        let time = match &tagger {
+            Some(tagger) => tagger.time.with_timezone(&Local),
+            None => Local
+                .timestamp_opt(commit.time().seconds(), 0)
+                .single()
+                .unwrap_or_else(Local::now),
+        };
+        tags.push(TagInfo {
+            name: name.to_string(),
+            target: commit.id(),
+            target_summary: commit.summary().unwrap_or_default().to_string(),
+            annotated: tag.is_some(),
+            message: tag
+                .as_ref()
+                .and_then(|tag| tag.message())
+                .map(|message| message.trim_end().to_string()),
+            tagger,
+            time,
+            ahead: None,
+        });
+    }
+
+    // Without the counts the tags are still worth listing.
+    if let Some(head) = repo.head().ok().and_then(|head| head.target()) {
+        let targets: Vec<Oid> = tags.iter().map(|tag| tag.target).collect();
+        if let Ok(counts) = commits_past(repo, head, &targets) {
+            for (tag, ahead) in tags.iter_mut().zip(counts) {
+                tag.ahead = ahead;
+            }
+        }
+    }
+
+    sort(&mut tags, TagOrder::default());
+    Ok(tags)
+}
+
+// How many commits `head` is past each of `targets`, or `None` for those
+// not in its history, in one walk: each target marks itself and its
+// ancestors with a bit, and every commit without a target's bit is one HEAD
+// is past it.
+fn commits_past(repo: &Repository, head: Oid, targets: &[Oid]) -> Result<Vec<Option<usize>>> {
+    let words = targets.len().div_ceil(64);
+    let mut tagged: HashMap<Oid, Vec<usize>> = HashMap::new();
+    for (i, target) in targets.iter().enumerate() {
+        tagged.entry(*target).or_default().push(i);
+    }
+    // Marks handed down to commits not yet walked.
+    let mut marks: HashMap<Oid, Vec<u64>> = HashMap::new();
+    let mut marked = vec![0; targets.len()];
+    let mut reached = vec![false; targets.len()];
+    let mut walked = 0;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    revwalk.push(head)?;
+    for oid in revwalk {
+        let oid = oid?;
+        walked += 1;
+        let mut mark = marks.remove(&oid).unwrap_or_else(|| vec![0; words]);
+        for &i in tagged.get(&oid).into_iter().flatten() {
+            mark[i / 64] |= 1 << (i % 64);
+            reached[i] = true;
+        }
+        if mark.iter().all(|word| *word == 0) {
+            continue;
+        }
+        for (w, &word) in mark.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                marked[w * 64 + word.trailing_zeros() as usize] += 1;
+                word &= word - 1;
+            }
+        }
+        for parent in repo.find_commit(oid)?.parent_ids() {
+            let parent_mark = marks.entry(parent).or_insert_with(|| vec![0; words]);
+            for (into, from) in parent_mark.iter_mut().zip(&mark) {
+                *into |= from;
+            }
+        }
+    }
+
+    Ok(reached
+        .iter()
+        .zip(marked)
+        .map(|(reached, marked)| reached.then_some(walked - marked))
+        .collect())
+}
+
+pub fn sort(tags: &mut [TagInfo], order: TagOrder) {
+    match order {
+        TagOrder::Version => tags.sort_by(|a, b| compare_versions(&b.name, &a.name)),
+        TagOrder::Date => tags.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.name.cmp(&b.name))),
+    }
+}
+
+// Compares names piece by piece, runs of digits by their value, so that
+// `v1.10.0` comes after `v1.9.2`.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let order = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+// The next run of digits, without leading zeros.
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        if !(digits.is_empty() && c == '0') {
+            digits.push(c);
+        }
+    }
+    digits
+}
+
+/// Tags commit `target` as `name`: an annotated tag if there is a message,
+/// otherwise a lightweight one.
+pub fn create(repo: &Repository, name: &str, target: Oid, message: Option<&str>) -> Result<()> {
+    if !Reference::is_valid_name(&format!("refs/tags/{}", name)) {
+        bail!("'{}' is not a valid tag name", name);
+    }
+    let commit = repo.find_commit(target)?;
+    let result = match message {
+        Some(message) => {
+            let tagger = repo
+                .signature()
+                .context("No signature configured; set user.name and user.email")?;
+            repo.tag(name, commit.as_object(), &tagger, message, false)
+        }
+        None => repo.tag_lightweight(name, commit.as_object(), false),
+    };
+    result.with_context(|| format!("Cannot create tag '{}'", name))?;
+    Ok(())
+}
+
+pub fn delete(repo: &Repository, name: &str) -> Result<()> {
+    repo.tag_delete(name)
+        .with_context(|| format!("Cannot delete tag '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(compare_versions("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("v1.9.2", "v1.10.0"), Ordering::Less);
+        assert_eq!(compare_versions("v2.0", "v10.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn leading_zeros_are_ignored() {
+        assert_eq!(compare_versions("v1.02", "v1.2"), Ordering::Equal);
+        assert_eq!(compare_versions("v1.010", "v1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("v0.0", "v0"), Ordering::Greater);
+
+        let mut chars = "007rest".chars().peekable();
+        assert_eq!(take_number(&mut chars), "7");
+        assert_eq!(chars.collect::<String>(), "rest");
+        assert_eq!(take_number(&mut "000".chars().peekable()), "");
+    }
+
+    #[test]
+    fn text_compares_character_by_character() {
+        assert_eq!(compare_versions("v1.0-rc1", "v1.0-rc2"), Ordering::Less);
+        assert_eq!(compare_versions("v1.0", "v1.0-rc1"), Ordering::Less);
+        assert_eq!(compare_versions("release", "release"), Ordering::Equal);
+    }
+
+    #[test]
+    fn version_order_puts_the_highest_first() {
+        let tag = |name: &str| TagInfo {
+            name: name.to_string(),
+            target: Oid::zero(),
+            target_summary: String::new(),
+            annotated: false,
+            tagger: None,
+            message: None,
+            time: Local::now(),
+            ahead: None,
+        };
+        let mut tags = ["v1.9", "v1.10", "v1.2", "v0.10"].map(tag);
+        sort(&mut tags, TagOrder::Version);
+        let names: Vec<&str> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(names, ["v1.10", "v1.9", "v1.2", "v0.10"]);
+    }
+
+    #[test]
+    fn ahead_counts_like_describe_across_merges() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Jane Doe", "jane@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let commit = |message: &str, parents: &[Oid]| {
+            let parents: Vec<_> = parents.iter().map(|id| repo.find_commit(*id).unwrap()).collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(None, &signature, &signature, message, &tree, &parents).unwrap()
+        };
+        //   root - main1 - main2 - merge (HEAD)
+        //       \- side1 ------/
+        //        \- other
+        let root = commit("root", &[]);
+        let main1 = commit("main1", &[root]);
+        let main2 = commit("main2", &[main1]);
+        let side1 = commit("side1", &[root]);
+        let merge = commit("merge", &[main2, side1]);
+        let other = commit("other", &[root]);
+        repo.set_head_detached(merge).unwrap();
+
+        let targets = [merge, main1, side1, root, other, main1];
+        let counts = commits_past(&repo, merge, &targets).unwrap();
+        assert_eq!(counts, [Some(0), Some(3), Some(3), Some(4), None, Some(3)]);
+        for (target, ahead) in targets.iter().zip(&counts) {
+            if let Some(ahead) = ahead {
+                assert_eq!(repo.graph_ahead_behind(merge, *target).unwrap(), (*ahead, 0));
+            }
+        }
+    }
+}
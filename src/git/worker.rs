@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Context, Result};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::git::repository::{GitRepository, RenameDetection};
+use crate::git::snapshot::{RefreshScope, RepoSnapshot};
+
+pub struct RefreshOutcome {
+    pub generation: u64,
+    pub scope: RefreshScope,
+    pub result: Result<RepoSnapshot>,
+}
+
+pub struct CommitOutcome {
+    pub amend: bool,
+    pub result: Result<git2::Oid>,
+}
+
+/// Computes `RepoSnapshot`s on tokio's blocking pool so that slow libgit2
+/// calls never stall the UI thread.
+pub struct StatusWorker {
+    runtime: Option<Runtime>,
+    repo_path: PathBuf,
+    rename_detection: RenameDetection,
+    generation: Arc<AtomicU64>,
+    pending: RefreshScope,
+    tx: UnboundedSender<RefreshOutcome>,
+    rx: UnboundedReceiver<RefreshOutcome>,
+    commit_tx: UnboundedSender<CommitOutcome>,
+    commit_rx: UnboundedReceiver<CommitOutcome>,
+}
+
+impl StatusWorker {
+    pub fn new(repo_path: &Path, rename_detection: RenameDetection) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("gittop-worker")
+            .build()
+            .context("Failed to start background worker")?;
+        let (tx, rx) = unbounded_channel();
+        let (commit_tx, commit_rx) = unbounded_channel();
+
+        Ok(Self {
+            runtime: Some(runtime),
+            repo_path: repo_path.to_path_buf(),
+            rename_detection,
+            generation: Arc::new(AtomicU64::new(0)),
+            pending: RefreshScope::NONE,
+            tx,
+            rx,
+            commit_tx,
+            commit_rx,
+        })
+    }
+
+    /// Starts a new refresh and returns its generation. Any refresh still in
+    /// flight becomes stale and stops at its next checkpoint; its scope is
+    /// folded into the new one so nothing it was asked for gets lost.
+    pub fn request_refresh(&mut self, scope: RefreshScope) -> u64 {
+        self.pending = self.pending.union(scope);
+        let scope = self.pending;
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let latest = Arc::clone(&self.generation);
+        let repo_path = self.repo_path.clone();
+        let rename_detection = self.rename_detection;
+        let tx = self.tx.clone();
+
+        if let Some(runtime) = &self.runtime {
+            runtime.spawn_blocking(move || {
+                let is_cancelled = || latest.load(Ordering::SeqCst) != generation;
+                // Every refresh that isn't superseded reports back, even if it
+                // panicked, so the UI never waits on it forever.
+                let capture = catch_unwind(AssertUnwindSafe(|| {
+                    let mut repo = GitRepository::open(&repo_path)?
+                        .with_rename_detection(rename_detection);
+                    RepoSnapshot::capture_until(&mut repo, scope, is_cancelled)
+                }));
+                let result = match capture {
+                    Ok(Ok(Some(snapshot))) => Ok(snapshot),
+                    Ok(Ok(None)) => return,
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err(anyhow!("the refresh crashed")),
+                };
+
+                if !is_cancelled() {
+                    let _ = tx.send(RefreshOutcome {
+                        generation,
+                        scope,
+                        result,
+                    });
+                }
+            });
+        }
+
+        generation
+    }
+
+    /// Commits the index, running the hooks, which can take a while. The
+    /// outcome comes back through `try_recv_commit`.
+    pub fn request_commit(&mut self, message: String, amend: bool) {
+        let repo_path = self.repo_path.clone();
+        let tx = self.commit_tx.clone();
+
+        if let Some(runtime) = &self.runtime {
+            runtime.spawn_blocking(move || {
+                let commit = catch_unwind(AssertUnwindSafe(|| {
+                    GitRepository::open(&repo_path)?.commit(&message, amend)
+                }));
+                let result = commit.unwrap_or_else(|_| Err(anyhow!("the commit crashed")));
+                let _ = tx.send(CommitOutcome { amend, result });
+            });
+        }
+    }
+
+    pub fn try_recv_commit(&mut self) -> Option<CommitOutcome> {
+        self.commit_rx.try_recv().ok()
+    }
+
+    pub fn latest_generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Returns the most recent finished refresh, discarding stale ones.
+    pub fn try_recv(&mut self) -> Option<RefreshOutcome> {
+        let mut latest = None;
+        while let Ok(outcome) = self.rx.try_recv() {
+            if outcome.generation == self.latest_generation() {
+                latest = Some(outcome);
+            }
+        }
+        if latest.is_some() {
+            self.pending = RefreshScope::NONE;
+        }
+        latest
+    }
+}
+
+impl Drop for StatusWorker {
+    fn drop(&mut self) {
+        // Don't wait for an in-flight `statuses()` call when quitting.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
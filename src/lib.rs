@@ -2,9 +2,9 @@ pub mod git;
 pub mod ui;
 pub mod watcher;
 
-pub use git::{GitRepository, FileStatus, CommitInfo, RemoteStatus};
-pub use ui::App;
-pub use watcher::FileSystemWatcher;
+pub use git::{GitRepository, FileStatus, CommitInfo, RemoteStatus, RenameDetection, RepoSnapshot};
+pub use ui::{App, AppOptions};
+pub use watcher::{FileSystemWatcher, WatchMode};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const NAME: &str = env!("CARGO_PKG_NAME");
\ No newline at end of file
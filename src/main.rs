@@ -2,8 +2,10 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use gittop::App;
+use gittop::ui::app::DEFAULT_HISTORY_DEPTH;
+use gittop::{App, AppOptions, RenameDetection, WatchMode};
 
 #[derive(Parser)]
 #[command(name = "gittop")]
@@ -13,6 +15,64 @@ struct Cli {
     /// Path to the Git repository (defaults to current directory)
     #[arg(value_name = "PATH")]
     path: Option<PathBuf>,
+
+    /// Poll for changes instead of relying on filesystem notifications
+    /// (for NFS, bind mounts, ...), optionally every INTERVAL (e.g. 500ms, 2s)
+    #[arg(
+        long,
+        value_name = "INTERVAL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1s",
+        value_parser = parse_interval
+    )]
+    poll: Option<Duration>,
+
+    /// Minimum similarity (in percent) for a deleted and an added file to be
+    /// shown as a rename
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value_t = 50,
+        value_parser = clap::value_parser!(u16).range(0..=100)
+    )]
+    rename_threshold: u16,
+
+    /// Also detect staged copies of existing files
+    #[arg(long)]
+    find_copies: bool,
+
+    /// Number of commits to load at first, and each time the commit list is
+    /// scrolled near the end of what is loaded
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = DEFAULT_HISTORY_DEPTH,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    history_depth: usize,
+}
+
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        (secs, 1.0)
+    } else {
+        (value, 1.0)
+    };
+
+    let secs = number
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("invalid interval '{}'", value))?
+        * scale;
+
+    if secs.is_finite() && secs > 0.0 {
+        Ok(Duration::from_secs_f64(secs))
+    } else {
+        Err(format!("interval must be positive, got '{}'", value))
+    }
 }
 
 fn main() -> Result<()> {
@@ -23,7 +83,16 @@ fn main() -> Result<()> {
         None => env::current_dir().context("Failed to get current directory")?,
     };
 
-    let mut app = App::new(repo_path)
+    let options = AppOptions {
+        watch_mode: cli.poll.map_or(WatchMode::Native, WatchMode::Poll),
+        rename_detection: RenameDetection {
+            threshold: cli.rename_threshold,
+            copies: cli.find_copies,
+        },
+        history_depth: cli.history_depth,
+    };
+
+    let mut app = App::with_options(repo_path, options)
         .context("Failed to initialize application")?;
     
     app.run()
@@ -31,3 +100,35 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_take_seconds_or_milliseconds() {
+        assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_interval("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_interval("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_interval(" 3 s"), Ok(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn intervals_must_be_positive_numbers() {
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("0ms").is_err());
+        assert!(parse_interval("-1s").is_err());
+        assert!(parse_interval("inf").is_err());
+        assert!(parse_interval("soon").is_err());
+        assert!(parse_interval("").is_err());
+    }
+
+    #[test]
+    fn poll_without_a_value_polls_every_second() {
+        let cli = Cli::try_parse_from(["gittop", "--poll"]).unwrap();
+        assert_eq!(cli.poll, Some(Duration::from_secs(1)));
+        let cli = Cli::try_parse_from(["gittop", "--poll=250ms"]).unwrap();
+        assert_eq!(cli.poll, Some(Duration::from_millis(250)));
+        assert!(Cli::try_parse_from(["gittop", "--poll=0s"]).is_err());
+    }
+}
@@ -5,34 +5,257 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use crate::git::GitRepository;
+use chrono::{DateTime, Local};
+
+use crate::git::snapshot::RefreshScope;
+use crate::git::status::FileStatusType;
+use crate::git::tags;
+use crate::git::worker::CommitOutcome;
+use crate::git::{
+    BranchInfo, CommitDetail, CommitFilter, CommitInfo, DetailEvent, DetailLoader, DiffSource, DiscardRecord, FileStatus,
+    GitRepository, HistoryEvent, HistoryLoader, HistoryScope, RenameDetection, RepoSnapshot, StashInfo, StatusEntry,
+    StatusWorker, TagInfo, TagOrder,
+};
+use crate::ui::commit_editor::{CommitEditor, EditorAction};
+use crate::ui::diff_view::DiffView;
 use crate::ui::events::{should_quit, should_refresh, AppEvent, EventHandler};
 use crate::ui::layout::draw_ui;
+use crate::ui::selection::ListSelection;
+use crate::watcher::{FileSystemWatcher, GitChangeEvent, WatchMode, WatchStatus};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TabType {
     Status,
     Commits,
+    Branches,
+    Tags,
+    Stash,
+    Discards,
+}
+
+impl TabType {
+    pub const ALL: [TabType; 6] = [
+        TabType::Status,
+        TabType::Commits,
+        TabType::Branches,
+        TabType::Tags,
+        TabType::Stash,
+        TabType::Discards,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TabType::Status => "Status",
+            TabType::Commits => "Commits",
+            TabType::Branches => "Branches",
+            TabType::Tags => "Tags",
+            TabType::Stash => "Stash",
+            TabType::Discards => "Discards",
+        }
+    }
+}
+
+/// The list panels that can hold the keyboard focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Staged,
+    Unstaged,
+    Changes,
+    Commits,
+    Diff,
+    Discards,
+    CommitFiles,
+    Branches,
+    Tags,
+    Stashes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexAction {
+    Stage,
+    Unstage,
+}
+
+impl IndexAction {
+    fn verb(self) -> &'static str {
+        match self {
+            IndexAction::Stage => "stage",
+            IndexAction::Unstage => "unstage",
+        }
+    }
+}
+
+/// What a stage/unstage key applies to, relative to the selected file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexTarget {
+    File,
+    Directory,
+    All,
+}
+
+/// A destructive action waiting for the user to confirm it.
+struct Confirmation {
+    prompt: String,
+    action: ConfirmedAction,
+}
+
+enum ConfirmedAction {
+    DiscardPaths(Vec<PathBuf>),
+    DiscardLines { path: PathBuf, patch: Vec<u8> },
+    RestoreDiscard(git2::Oid),
+    /// Stash the local changes, then switch to the branch.
+    StashAndCheckout(String),
+    DeleteBranch { name: String, force: bool },
+    DropStash(git2::Oid),
+    DeleteTag(String),
+}
+
+/// A one-line text prompt in the footer, and what its answer is for.
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+}
+
+enum PromptKind {
+    Search,
+    CreateBranch(git2::Oid),
+    RenameBranch(String),
+    StashPush { include_untracked: bool },
+    CreateTag(git2::Oid),
+    /// The annotation for a tag being created; without one it's lightweight.
+    TagMessage { name: String, target: git2::Oid },
+}
+
+/// How often status is re-read when the work tree itself can't be watched.
+const DEGRADED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many commits are loaded up front, and then per page as the commit
+/// list is scrolled, unless configured otherwise.
+pub const DEFAULT_HISTORY_DEPTH: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct AppOptions {
+    pub watch_mode: WatchMode,
+    pub rename_detection: RenameDetection,
+    pub history_depth: usize,
+}
+
+impl Default for AppOptions {
+    fn default() -> Self {
+        Self {
+            watch_mode: WatchMode::default(),
+            rename_detection: RenameDetection::default(),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+        }
+    }
 }
 
 pub struct App {
     repo: GitRepository,
+    options: AppOptions,
+    snapshot: RepoSnapshot,
+    worker: StatusWorker,
+    refreshing: bool,
+    last_change: Option<(String, DateTime<Local>)>,
     should_quit: bool,
     current_tab: TabType,
+    combined_status: bool,
+    focus: Panel,
+    staged_list: ListSelection,
+    unstaged_list: ListSelection,
+    changes_list: ListSelection,
+    commits: Vec<CommitInfo>,
+    commits_list: ListSelection,
+    history: HistoryScope,
+    history_loader: HistoryLoader,
+    detail_loader: DetailLoader,
+    commit_filter: Option<CommitFilter>,
+    /// Positions in `commits` of those that match the filter.
+    commit_matches: Vec<usize>,
+    /// `n` is waiting for a match at or after this position to load.
+    seeking_match: Option<usize>,
+    prompt: Option<Prompt>,
+    commit_detail: Option<CommitDetail>,
+    /// A commit's details have been asked for and haven't arrived yet.
+    loading_commit: bool,
+    /// Move the focus to the commit's files once they arrive.
+    open_commit_on_load: bool,
+    commit_files_list: ListSelection,
+    branches_list: ListSelection,
+    tags_list: ListSelection,
+    tag_order: TagOrder,
+    stashes_list: ListSelection,
+    discards: Vec<DiscardRecord>,
+    discards_list: ListSelection,
+    confirmation: Option<Confirmation>,
+    commit_editor: Option<CommitEditor>,
+    commit_draft: Option<CommitEditor>,
+    diff_view: Option<DiffView>,
+    diff_origin: Panel,
     last_error: Option<String>,
+    watch_status: Option<WatchStatus>,
+    last_status_poll: Instant,
 }
 
 impl App {
     pub fn new(repo_path: PathBuf) -> Result<Self> {
-        let repo = GitRepository::open(&repo_path)?;
-        
+        Self::with_options(repo_path, AppOptions::default())
+    }
+
+    pub fn with_options(repo_path: PathBuf, options: AppOptions) -> Result<Self> {
+        let repo = GitRepository::open(&repo_path)?
+            .with_rename_detection(options.rename_detection);
+        let mut worker = StatusWorker::new(repo.path(), options.rename_detection)?;
+        worker.request_refresh(RefreshScope::ALL);
+        let mut history_loader = HistoryLoader::new(repo.path())?;
+        history_loader.reset(HistoryScope::default(), None, options.history_depth);
+        let detail_loader = DetailLoader::new(repo.path(), options.rename_detection)?;
+
         Ok(Self {
             repo,
+            options,
+            snapshot: RepoSnapshot::default(),
+            worker,
+            refreshing: true,
+            last_change: None,
             should_quit: false,
             current_tab: TabType::Status,
+            combined_status: false,
+            focus: Panel::Staged,
+            staged_list: ListSelection::default(),
+            unstaged_list: ListSelection::default(),
+            changes_list: ListSelection::default(),
+            commits: Vec::new(),
+            commits_list: ListSelection::default(),
+            history: HistoryScope::default(),
+            history_loader,
+            detail_loader,
+            commit_filter: None,
+            commit_matches: Vec::new(),
+            seeking_match: None,
+            prompt: None,
+            commit_detail: None,
+            loading_commit: false,
+            open_commit_on_load: false,
+            commit_files_list: ListSelection::default(),
+            branches_list: ListSelection::default(),
+            tags_list: ListSelection::default(),
+            tag_order: TagOrder::default(),
+            stashes_list: ListSelection::default(),
+            discards: Vec::new(),
+            discards_list: ListSelection::default(),
+            confirmation: None,
+            commit_editor: None,
+            commit_draft: None,
+            diff_view: None,
+            diff_origin: Panel::Staged,
             last_error: None,
+            watch_status: None,
+            last_status_poll: Instant::now(),
         })
     }
 
@@ -43,7 +266,14 @@ impl App {
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
-        let event_handler = EventHandler::new(Duration::from_millis(100));
+        let mut event_handler = EventHandler::new(Duration::from_millis(100));
+        match self.create_watcher() {
+            Ok(watcher) => {
+                event_handler = event_handler.with_watcher(watcher, Duration::from_millis(300));
+            }
+            Err(e) => self.set_error(format!("File watching disabled: {}", e)),
+        }
+
         let res = self.run_loop(&mut terminal, event_handler);
 
         disable_raw_mode()?;
@@ -53,17 +283,35 @@ impl App {
         res
     }
 
+    fn create_watcher(&self) -> Result<FileSystemWatcher> {
+        let mut watcher = FileSystemWatcher::new(self.options.watch_mode)?;
+        watcher.watch(self.repo.path())?;
+        Ok(watcher)
+    }
+
     fn run_loop<B: ratatui::backend::Backend>(
         &mut self,
         terminal: &mut Terminal<B>,
-        event_handler: EventHandler,
+        mut event_handler: EventHandler,
     ) -> Result<()> {
         while !self.should_quit {
+            self.poll_worker();
+            self.poll_history();
+            self.poll_details();
             self.draw(terminal)?;
             
-            match event_handler.next()? {
+            let event = event_handler.next_event()?;
+            self.watch_status = event_handler.watch_status();
+
+            match event {
                 AppEvent::KeyPress(key) => {
-                    if should_quit(&key) {
+                    if self.commit_editor.is_some() {
+                        self.handle_editor_key(key);
+                    } else if self.prompt.is_some() {
+                        self.handle_prompt_key(key);
+                    } else if self.confirmation.is_some() {
+                        self.handle_confirmation_key(key);
+                    } else if should_quit(&key) {
                         self.should_quit = true;
                     } else if should_refresh(&key) {
                         self.update()?;
@@ -71,17 +319,20 @@ impl App {
                         self.handle_key_event(key)?;
                     }
                 }
-                AppEvent::Tick => {
-                    // Future: Auto-refresh logic
+                AppEvent::FileSystemChange(changes) => {
+                    self.handle_changes(changes);
+                }
+                AppEvent::Tick => self.poll_status_if_degraded(),
+                AppEvent::Quit => {
+                    self.should_quit = true;
                 }
-                _ => {}
             }
         }
         
         Ok(())
     }
 
-    fn draw<B: ratatui::backend::Backend>(&self, terminal: &mut Terminal<B>) -> Result<()> {
+    fn draw<B: ratatui::backend::Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
         terminal.draw(|f| {
             if let Err(e) = draw_ui(f, self) {
                 eprintln!("Failed to draw UI: {}", e);
@@ -97,24 +348,1122 @@ impl App {
             KeyCode::Tab => {
                 self.current_tab = match self.current_tab {
                     TabType::Status => TabType::Commits,
-                    TabType::Commits => TabType::Status,
+                    TabType::Commits => TabType::Branches,
+                    TabType::Branches => TabType::Tags,
+                    TabType::Tags => TabType::Stash,
+                    TabType::Stash => TabType::Discards,
+                    TabType::Discards => TabType::Status,
                 };
+                if self.current_tab == TabType::Discards {
+                    self.load_discards();
+                }
+                if self.diff_view.is_some() && !self.visible_panels().contains(&self.diff_origin) {
+                    self.close_diff();
+                }
+                self.clamp_focus();
             }
-            _ => {}
+            KeyCode::Char('c') if self.current_tab == TabType::Status => {
+                self.combined_status = !self.combined_status;
+                self.clamp_focus();
+                if !self.is_file_panel(self.focus) {
+                    self.diff_origin = self.visible_panels()[0];
+                }
+                self.follow_focus();
+                self.reload_diff();
+            }
+            KeyCode::Char('C') if self.current_tab == TabType::Status => {
+                self.commit_editor = Some(self.commit_draft.take().unwrap_or_default());
+            }
+            KeyCode::Char('w') if self.current_tab == TabType::Commits => self.toggle_history(),
+            KeyCode::Char('/') if self.focus == Panel::Commits => {
+                let query = self.commit_filter.as_ref().map(|filter| filter.query().to_string());
+                self.open_prompt(PromptKind::Search, &query.unwrap_or_default());
+            }
+            KeyCode::Char('b') if self.focus == Panel::Commits => {
+                if let Some(commit) = self.selected_commit() {
+                    match git2::Oid::from_str(&commit.hash) {
+                        Ok(id) => self.open_prompt(PromptKind::CreateBranch(id), ""),
+                        Err(e) => self.set_error(e.to_string()),
+                    }
+                }
+            }
+            KeyCode::Enter if self.focus == Panel::Branches => self.checkout_selected_branch(),
+            KeyCode::Char('n') if self.focus == Panel::Branches => {
+                if let Some(branch) = self.selected_branch() {
+                    let tip = branch.tip;
+                    self.open_prompt(PromptKind::CreateBranch(tip), "");
+                }
+            }
+            KeyCode::Char('m') if self.focus == Panel::Branches => {
+                if let Some(branch) = self.selected_branch() {
+                    let name = branch.name.clone();
+                    self.open_prompt(PromptKind::RenameBranch(name.clone()), &name);
+                }
+            }
+            KeyCode::Char('d') if self.focus == Panel::Branches => self.confirm_delete_branch(),
+            KeyCode::Char('t') if self.focus == Panel::Commits => {
+                if let Some(commit) = self.selected_commit() {
+                    match git2::Oid::from_str(&commit.hash) {
+                        Ok(id) => self.open_prompt(PromptKind::CreateTag(id), ""),
+                        Err(e) => self.set_error(e.to_string()),
+                    }
+                }
+            }
+            KeyCode::Char('o') if self.focus == Panel::Tags => self.toggle_tag_order(),
+            KeyCode::Char('n') if self.focus == Panel::Tags => match self.repo.head_id() {
+                Ok(head) => self.open_prompt(PromptKind::CreateTag(head), ""),
+                Err(e) => self.set_error(format!("Nothing to tag: {}", e)),
+            },
+            KeyCode::Char('d') if self.focus == Panel::Tags => self.confirm_delete_tag(),
+            KeyCode::Enter if self.focus == Panel::Stashes => self.open_diff(),
+            KeyCode::Char('s') if self.focus == Panel::Stashes => {
+                self.open_prompt(PromptKind::StashPush { include_untracked: false }, "")
+            }
+            KeyCode::Char('S') if self.focus == Panel::Stashes => {
+                self.open_prompt(PromptKind::StashPush { include_untracked: true }, "")
+            }
+            KeyCode::Char('a') if self.focus == Panel::Stashes => self.apply_selected_stash(false),
+            KeyCode::Char('p') if self.focus == Panel::Stashes => self.apply_selected_stash(true),
+            KeyCode::Char('d') if self.focus == Panel::Stashes => self.confirm_drop_stash(),
+            KeyCode::Char('n') if self.focus == Panel::Commits && self.commit_filter.is_some() => {
+                self.jump_to_match(true)
+            }
+            KeyCode::Char('N') if self.focus == Panel::Commits && self.commit_filter.is_some() => {
+                self.jump_to_match(false)
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.cycle_focus(-1),
+            KeyCode::Right | KeyCode::Char('l') => self.cycle_focus(1),
+            KeyCode::Enter if self.is_file_panel(self.focus) => self.open_diff(),
+            KeyCode::Enter if self.focus == Panel::Discards => self.confirm_restore(),
+            KeyCode::Enter if self.focus == Panel::Commits => self.open_commit_detail(),
+            KeyCode::Char('d') if self.focus == Panel::Diff => self.confirm_discard_lines(),
+            KeyCode::Char('d') if self.is_file_panel(self.focus) => self.confirm_discard_file(),
+            KeyCode::Esc if self.diff_view.as_ref().is_some_and(DiffView::has_range) => {
+                if let Some(view) = &mut self.diff_view {
+                    view.clear_range();
+                }
+            }
+            KeyCode::Esc if self.diff_view.is_some() => self.close_diff(),
+            KeyCode::Esc if self.commit_detail.is_some() => self.close_commit_detail(),
+            KeyCode::Esc if self.focus == Panel::Commits && self.commit_filter.is_some() => {
+                self.set_commit_filter(None)
+            }
+            KeyCode::Char('s') if self.focus == Panel::Diff => {
+                self.apply_diff_selection(IndexAction::Stage)
+            }
+            KeyCode::Char('u') if self.focus == Panel::Diff => {
+                self.apply_diff_selection(IndexAction::Unstage)
+            }
+            KeyCode::Char('s') => self.update_index(IndexAction::Stage, IndexTarget::File),
+            KeyCode::Char('u') => self.update_index(IndexAction::Unstage, IndexTarget::File),
+            KeyCode::Char('S') => self.update_index(IndexAction::Stage, IndexTarget::Directory),
+            KeyCode::Char('U') => self.update_index(IndexAction::Unstage, IndexTarget::Directory),
+            KeyCode::Char('a') if self.current_tab == TabType::Status => {
+                self.update_index(IndexAction::Stage, IndexTarget::All)
+            }
+            KeyCode::Char('A') if self.current_tab == TabType::Status => {
+                self.update_index(IndexAction::Unstage, IndexTarget::All)
+            }
+            KeyCode::Char('v') => {
+                if let Some(view) = &mut self.diff_view {
+                    view.toggle_layout();
+                }
+            }
+            code if self.focus == Panel::Diff => self.scroll_diff(code),
+            code => self.move_selection(code),
         }
         
         Ok(())
     }
 
+    fn move_selection(&mut self, code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        let Some(list) = self.list_mut(self.focus) else {
+            return;
+        };
+        let before = list.selected();
+        match code {
+            KeyCode::Down | KeyCode::Char('j') => list.next(),
+            KeyCode::Up | KeyCode::Char('k') => list.previous(),
+            KeyCode::PageDown => list.page_down(),
+            KeyCode::PageUp => list.page_up(),
+            KeyCode::Home | KeyCode::Char('g') => list.first(),
+            KeyCode::End | KeyCode::Char('G') => list.last(),
+            _ => return,
+        }
+        let moved = list.selected() != before;
+
+        if self.focus == Panel::Commits {
+            self.load_more_history();
+        }
+        if !moved {
+            return;
+        }
+        if self.is_file_panel(self.focus) || self.focus == Panel::Stashes {
+            self.reload_diff();
+        } else if self.focus == Panel::Commits && self.commit_detail.is_some() {
+            self.load_commit_detail();
+        }
+    }
+
+    fn scroll_diff(&mut self, code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        let Some(view) = &mut self.diff_view else {
+            return;
+        };
+        match code {
+            KeyCode::Down | KeyCode::Char('j') => view.move_cursor(1),
+            KeyCode::Up | KeyCode::Char('k') => view.move_cursor(-1),
+            KeyCode::PageDown => view.page_down(),
+            KeyCode::PageUp => view.page_up(),
+            KeyCode::Home | KeyCode::Char('g') => view.cursor_to_top(),
+            KeyCode::End | KeyCode::Char('G') => view.cursor_to_bottom(),
+            KeyCode::Char(']') => view.jump_hunk(true),
+            KeyCode::Char('[') => view.jump_hunk(false),
+            KeyCode::Char('V') | KeyCode::Char(' ') => view.toggle_range(),
+            _ => {}
+        }
+    }
+
+    pub fn visible_panels(&self) -> &'static [Panel] {
+        let diff = self.diff_view.is_some();
+        match self.current_tab {
+            TabType::Status => match (self.combined_status, diff) {
+                (false, false) => &[Panel::Staged, Panel::Unstaged, Panel::Commits],
+                (false, true) => &[Panel::Staged, Panel::Unstaged, Panel::Diff],
+                (true, false) => &[Panel::Changes, Panel::Commits],
+                (true, true) => &[Panel::Changes, Panel::Diff],
+            },
+            TabType::Commits => match (self.commit_detail.is_some(), diff) {
+                (false, _) => &[Panel::Commits],
+                (true, false) => &[Panel::Commits, Panel::CommitFiles],
+                (true, true) => &[Panel::CommitFiles, Panel::Diff],
+            },
+            TabType::Branches => &[Panel::Branches],
+            TabType::Tags => &[Panel::Tags],
+            TabType::Stash if diff => &[Panel::Stashes, Panel::Diff],
+            TabType::Stash => &[Panel::Stashes],
+            TabType::Discards => &[Panel::Discards],
+        }
+    }
+
+    fn is_file_panel(&self, panel: Panel) -> bool {
+        matches!(
+            panel,
+            Panel::Staged | Panel::Unstaged | Panel::Changes | Panel::CommitFiles
+        )
+    }
+
+    fn toggle_history(&mut self) {
+        self.history = match self.history {
+            HistoryScope::Head => HistoryScope::AllRefs,
+            HistoryScope::AllRefs => HistoryScope::Head,
+        };
+        self.seeking_match = None;
+        self.history_loader
+            .reset(self.history, self.commit_filter.clone(), self.options.history_depth);
+    }
+
+    fn open_prompt(&mut self, kind: PromptKind, input: &str) {
+        self.prompt = Some(Prompt {
+            kind,
+            input: input.to_string(),
+        });
+    }
+
+    fn handle_prompt_key(&mut self, key: crossterm::event::KeyEvent) {
+        use crossterm::event::{KeyCode, KeyModifiers};
+
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.submit_prompt(prompt);
+                }
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                prompt.input.push(c)
+            }
+            _ => {}
+        }
+    }
+
+    fn submit_prompt(&mut self, prompt: Prompt) {
+        let input = prompt.input.trim();
+        match prompt.kind {
+            PromptKind::Search if input.is_empty() => self.set_commit_filter(None),
+            PromptKind::Search => match CommitFilter::parse(input) {
+                Ok(filter) => self.set_commit_filter(Some(filter)),
+                Err(e) => self.set_error(format!("{:#}", e)),
+            },
+            PromptKind::StashPush { include_untracked } => {
+                let message = (!input.is_empty()).then_some(input);
+                let result = self.repo.stash_push(message, include_untracked);
+                self.operation_done(result.map(|_| "Stashed local changes".to_string()));
+            }
+            PromptKind::TagMessage { name, target } => {
+                let message = (!input.is_empty()).then_some(input);
+                let result = self.repo.create_tag(&name, target, message);
+                self.operation_done(result.map(|()| format!("Created tag {}", name)));
+            }
+            _ if input.is_empty() => {}
+            PromptKind::CreateTag(target) => {
+                let name = input.to_string();
+                self.open_prompt(PromptKind::TagMessage { name, target }, "");
+            }
+            PromptKind::CreateBranch(target) => {
+                let result = self.repo.create_branch(input, target);
+                self.operation_done(result.map(|()| format!("Created branch {}", input)));
+            }
+            PromptKind::RenameBranch(name) => {
+                if input != name {
+                    let result = self.repo.rename_branch(&name, input);
+                    self.operation_done(result.map(|()| format!("Renamed {} to {}", name, input)));
+                }
+            }
+        }
+    }
+
+    fn selected_commit(&self) -> Option<&CommitInfo> {
+        self.commits_list.selected().and_then(|i| self.commits.get(i))
+    }
+
+    fn selected_branch(&self) -> Option<&BranchInfo> {
+        self.branches_list.selected().and_then(|i| self.snapshot.branches.get(i))
+    }
+
+    // Re-sorts the tags, keeping the same one selected.
+    fn toggle_tag_order(&mut self) {
+        self.tag_order = match self.tag_order {
+            TagOrder::Version => TagOrder::Date,
+            TagOrder::Date => TagOrder::Version,
+        };
+        let selected = self.selected_tag().map(|tag| tag.name.clone());
+        tags::sort(&mut self.snapshot.tags, self.tag_order);
+        if let Some(i) = selected.and_then(|name| self.snapshot.tags.iter().position(|tag| tag.name == name)) {
+            self.tags_list.select(i);
+        }
+    }
+
+    pub fn selected_tag(&self) -> Option<&TagInfo> {
+        self.tags_list.selected().and_then(|i| self.snapshot.tags.get(i))
+    }
+
+    fn selected_stash(&self) -> Option<&StashInfo> {
+        self.stashes_list.selected().and_then(|i| self.snapshot.stashes.get(i))
+    }
+
+    /// Switches to the selected branch; with local changes, offers to stash
+    /// them first rather than carry them over.
+    fn checkout_selected_branch(&mut self) {
+        let Some(branch) = self.selected_branch() else {
+            return;
+        };
+        let name = branch.name.clone();
+        let dirty = self.snapshot.entries.iter().any(|entry| {
+            entry.index.is_some()
+                || entry.worktree.as_ref().is_some_and(|status| *status != FileStatusType::Untracked)
+        });
+        if dirty {
+            self.confirmation = Some(Confirmation {
+                prompt: format!("You have local changes. Stash them and switch to {}?", name),
+                action: ConfirmedAction::StashAndCheckout(name),
+            });
+            return;
+        }
+        let result = self.repo.checkout_branch(&name);
+        self.operation_done(result.map(|()| format!("Switched to {}", name)));
+    }
+
+    fn confirm_delete_branch(&mut self) {
+        let Some(branch) = self.selected_branch() else {
+            return;
+        };
+        let name = branch.name.clone();
+        if branch.is_head {
+            self.set_error("Cannot delete the branch you are on".to_string());
+            return;
+        }
+        let (prompt, force) = match self.repo.is_branch_merged(&name) {
+            Ok(true) => (format!("Delete branch {}?", name), false),
+            Ok(false) => (
+                format!("Branch {} is not fully merged. Delete it anyway, losing its commits?", name),
+                true,
+            ),
+            Err(e) => {
+                self.set_error(format!("Failed to check branch {}: {}", name, e));
+                return;
+            }
+        };
+        self.confirmation = Some(Confirmation {
+            prompt,
+            action: ConfirmedAction::DeleteBranch { name, force },
+        });
+    }
+
+    fn apply_selected_stash(&mut self, pop: bool) {
+        let Some(stash) = self.selected_stash() else {
+            return;
+        };
+        let (id, index) = (stash.id, stash.index);
+        let result = self.repo.stash_apply(id, pop).map(|()| {
+            let verb = if pop { "Popped" } else { "Applied" };
+            format!("{} stash@{{{}}}", verb, index)
+        });
+        self.operation_done(result);
+    }
+
+    fn confirm_drop_stash(&mut self) {
+        let Some(stash) = self.selected_stash() else {
+            return;
+        };
+        self.confirmation = Some(Confirmation {
+            prompt: format!("Drop stash@{{{}}}: {}?", stash.index, stash.message),
+            action: ConfirmedAction::DropStash(stash.id),
+        });
+    }
+
+    fn confirm_delete_tag(&mut self) {
+        let Some(tag) = self.selected_tag() else {
+            return;
+        };
+        let name = tag.name.clone();
+        self.confirmation = Some(Confirmation {
+            prompt: format!("Delete tag {}? Copies already pushed stay on the remote.", name),
+            action: ConfirmedAction::DeleteTag(name),
+        });
+    }
+
+    /// Reports the outcome of a branch, tag or stash operation and shows its
+    /// effects.
+    fn operation_done(&mut self, result: Result<String>) {
+        match result {
+            Ok(description) => {
+                self.last_error = None;
+                self.last_change = Some((description, Local::now()));
+                self.refresh(RefreshScope::ALL);
+            }
+            Err(e) => self.set_error(format!("{:#}", e)),
+        }
+    }
+
+    /// Marks the commits matching `filter` and selects the first, or
+    /// clears the marks.
+    fn set_commit_filter(&mut self, filter: Option<CommitFilter>) {
+        self.commit_filter = filter;
+        self.commit_matches.clear();
+        self.seeking_match = None;
+        self.reload_history();
+        if self.commit_filter.is_some() {
+            self.seeking_match = Some(0);
+            self.history_loader.find_match();
+        }
+    }
+
+    /// Selects the next match below the selection, or above it going back,
+    /// loading more of the history to find one if need be.
+    fn jump_to_match(&mut self, forward: bool) {
+        let selected = self.commits_list.selected().unwrap_or(0);
+        let found = if forward {
+            self.commit_matches.iter().find(|&&i| i > selected)
+        } else {
+            self.commit_matches.iter().rev().find(|&&i| i < selected)
+        };
+        match found {
+            Some(&i) => self.select_commit(i),
+            None if forward && !self.history_loader.is_done() => {
+                self.seeking_match = Some(selected + 1);
+                self.history_loader.find_match();
+            }
+            None => {}
+        }
+    }
+
+    fn select_commit(&mut self, index: usize) {
+        let before = self.commits_list.selected();
+        self.commits_list.select(index);
+        self.load_more_history();
+        if self.commits_list.selected() != before && self.commit_detail.is_some() {
+            self.load_commit_detail();
+        }
+    }
+
+    /// Reloads the history, keeping at least as many commits as are loaded
+    /// now so that the selection stays in place.
+    fn reload_history(&mut self) {
+        let count = self.commits.len().max(self.options.history_depth);
+        self.history_loader
+            .reset(self.history, self.commit_filter.clone(), count);
+        if self.seeking_match.is_some() {
+            self.history_loader.find_match();
+        }
+    }
+
+    /// Fetches the next page once the selection gets near the end of what
+    /// is loaded.
+    fn load_more_history(&mut self) {
+        let remaining = self.commits.len() - self.commits_list.selected().unwrap_or(0);
+        if remaining <= self.options.history_depth / 2 {
+            self.history_loader.load_more(self.options.history_depth);
+        }
+    }
+
+    fn poll_history(&mut self) {
+        while let Some(event) = self.history_loader.try_recv() {
+            match event {
+                HistoryEvent::Page {
+                    commits,
+                    matches,
+                    reset,
+                    done,
+                    ..
+                } => {
+                    let selected = self
+                        .commits_list
+                        .selected()
+                        .and_then(|i| self.commits.get(i))
+                        .map(|commit| commit.hash.clone());
+                    if reset {
+                        self.commits = commits;
+                        self.commit_matches = matches;
+                    } else {
+                        let start = self.commits.len();
+                        self.commits.extend(commits);
+                        self.commit_matches.extend(matches.into_iter().map(|i| start + i));
+                    }
+                    self.commits_list.set_len(self.commits.len());
+                    // Follow the selected commit if new ones came in above it.
+                    if let Some(i) = selected
+                        .and_then(|hash| self.commits.iter().position(|c| c.hash == hash))
+                    {
+                        self.commits_list.select(i);
+                    }
+                    if let Some(from) = self.seeking_match {
+                        if let Some(&i) = self.commit_matches.iter().find(|&&i| i >= from) {
+                            self.seeking_match = None;
+                            self.select_commit(i);
+                        } else if done {
+                            self.seeking_match = None;
+                        }
+                    }
+                    self.load_more_history();
+                }
+                HistoryEvent::Total { .. } => {}
+                HistoryEvent::Failed { error, .. } => {
+                    self.set_error(format!("Failed to load history: {}", error))
+                }
+            }
+        }
+    }
+
+    fn open_commit_detail(&mut self) {
+        self.current_tab = TabType::Commits;
+        if self.diff_view.is_some() {
+            self.close_diff();
+        }
+        self.load_commit_detail();
+        self.open_commit_on_load = self.loading_commit;
+    }
+
+    fn close_commit_detail(&mut self) {
+        self.commit_detail = None;
+        self.loading_commit = false;
+        self.open_commit_on_load = false;
+        self.focus = Panel::Commits;
+    }
+
+    /// Shows the commit selected in the Recent Commits list, once the
+    /// detail loader has read it.
+    fn load_commit_detail(&mut self) {
+        let Some(commit) = self.selected_commit() else {
+            return;
+        };
+        match git2::Oid::from_str(&commit.hash) {
+            Ok(id) => {
+                let refs = commit.refs.clone();
+                self.detail_loader.load_commit(id, refs);
+                self.loading_commit = true;
+            }
+            Err(e) => self.set_error(format!("Failed to load commit: {}", e)),
+        }
+    }
+
+    fn commit_detail_loaded(&mut self, result: Result<Box<CommitDetail>>) {
+        // Closed while it was loading.
+        if !self.loading_commit {
+            return;
+        }
+        self.loading_commit = false;
+        match result {
+            Ok(detail) => {
+                self.commit_files_list.set_len(detail.files.len());
+                self.commit_files_list.first();
+                self.commit_detail = Some(*detail);
+                if std::mem::take(&mut self.open_commit_on_load) {
+                    self.focus = Panel::CommitFiles;
+                    self.follow_focus();
+                }
+            }
+            Err(e) => {
+                self.open_commit_on_load = false;
+                self.set_error(format!("Failed to load commit: {}", e));
+            }
+        }
+    }
+
+    fn open_diff(&mut self) {
+        self.diff_origin = self.focus;
+        self.diff_view = Some(DiffView::new(Vec::new()));
+        self.reload_diff();
+        self.focus = Panel::Diff;
+    }
+
+    fn close_diff(&mut self) {
+        self.diff_view = None;
+        if self.focus == Panel::Diff {
+            self.focus = self.diff_origin;
+        }
+        self.clamp_focus();
+    }
+
+    /// Points the diff pane, if open, at the currently selected file. The
+    /// diff is loaded in the background and shows up in `poll_details`.
+    fn reload_diff(&mut self) {
+        if self.diff_view.is_none() {
+            return;
+        }
+        let parts = self.selected_diff_parts();
+        self.detail_loader.load_diff(parts);
+        if let Some(view) = &mut self.diff_view {
+            view.set_loading();
+        }
+    }
+
+    /// What the diff pane should show, as the paths to diff for each source.
+    fn selected_diff_parts(&self) -> Vec<(DiffSource, Vec<PathBuf>)> {
+        let part = |file: Option<&FileStatus>, source| match file {
+            Some(file) => vec![(source, file_paths(file).into_iter().map(Path::to_path_buf).collect())],
+            None => Vec::new(),
+        };
+        match self.diff_origin {
+            Panel::Staged => part(self.selected_staged(), DiffSource::Staged),
+            Panel::Unstaged => part(self.selected_unstaged(), DiffSource::Unstaged),
+            Panel::Changes => {
+                let Some(entry) = self
+                    .changes_list
+                    .selected()
+                    .and_then(|i| self.snapshot.entries.get(i))
+                else {
+                    return Vec::new();
+                };
+                let mut parts = part(entry.staged().as_ref(), DiffSource::Staged);
+                parts.extend(part(entry.unstaged().as_ref(), DiffSource::Unstaged));
+                parts
+            }
+            Panel::CommitFiles => {
+                let Some(detail) = &self.commit_detail else {
+                    return Vec::new();
+                };
+                let file = self.commit_files_list.selected().and_then(|i| detail.files.get(i));
+                part(file, DiffSource::Commit(detail.id))
+            }
+            Panel::Stashes => self
+                .selected_stash()
+                .map(|stash| (DiffSource::Stash(stash.id), Vec::new()))
+                .into_iter()
+                .collect(),
+            Panel::Commits | Panel::Diff | Panel::Discards | Panel::Branches | Panel::Tags => {
+                Vec::new()
+            }
+        }
+    }
+
+    fn poll_details(&mut self) {
+        while let Some(event) = self.detail_loader.try_recv() {
+            match event {
+                DetailEvent::Diff { result, .. } => {
+                    let files = match result {
+                        Ok(files) => files,
+                        Err(e) => {
+                            self.set_error(format!("Failed to load diff: {}", e));
+                            Vec::new()
+                        }
+                    };
+                    if let Some(view) = &mut self.diff_view {
+                        view.set_files(files);
+                    }
+                }
+                DetailEvent::Commit { result, .. } => self.commit_detail_loaded(result),
+            }
+        }
+    }
+
+    fn update_index(&mut self, action: IndexAction, target: IndexTarget) {
+        let result = match target {
+            IndexTarget::All => match action {
+                IndexAction::Stage => self.repo.stage_all(),
+                IndexAction::Unstage => self.repo.unstage_all(),
+            },
+            IndexTarget::File | IndexTarget::Directory => {
+                let Some(file) = self.selected_file(action) else {
+                    return;
+                };
+                let directory;
+                let paths = if target == IndexTarget::Directory {
+                    directory = containing_directory(&file.path);
+                    vec![directory.as_path()]
+                } else {
+                    file_paths(&file)
+                };
+                // The root directory is every path in the repository.
+                match (action, paths[0].as_os_str().is_empty()) {
+                    (IndexAction::Stage, true) => self.repo.stage_all(),
+                    (IndexAction::Unstage, true) => self.repo.unstage_all(),
+                    (IndexAction::Stage, false) => self.repo.stage_paths(&paths),
+                    (IndexAction::Unstage, false) => self.repo.unstage_paths(&paths),
+                }
+            }
+        };
+
+        match result {
+            Ok(()) => self.refresh(RefreshScope {
+                status: true,
+                ..RefreshScope::NONE
+            }),
+            Err(e) => self.set_error(format!("Failed to {}: {}", action.verb(), e)),
+        }
+    }
+
+    /// Stages or unstages the hunk or lines selected in the diff pane.
+    fn apply_diff_selection(&mut self, action: IndexAction) {
+        let Some(view) = self.diff_view.as_ref().filter(|view| !view.is_loading()) else {
+            return;
+        };
+        let Some((file, selected)) = view.selection() else {
+            return;
+        };
+        let file = &view.files()[file];
+        let result = match (action, file.source) {
+            (IndexAction::Stage, DiffSource::Staged) => {
+                Err(anyhow::anyhow!("these changes are already staged"))
+            }
+            (IndexAction::Unstage, DiffSource::Unstaged) => {
+                Err(anyhow::anyhow!("these changes aren't staged"))
+            }
+            _ => file
+                .index_patch(&selected)
+                .and_then(|patch| self.repo.apply_to_index(&patch)),
+        };
+
+        match result {
+            Ok(()) => {
+                self.reload_diff();
+                self.refresh(RefreshScope {
+                    status: true,
+                    ..RefreshScope::NONE
+                });
+            }
+            Err(e) => self.set_error(format!("Failed to {}: {}", action.verb(), e)),
+        }
+    }
+
+    /// The file an index action applies to: the selection of the focused
+    /// file panel, or of the panel the diff pane follows.
+    fn selected_file(&self, action: IndexAction) -> Option<FileStatus> {
+        let panel = if self.focus == Panel::Diff {
+            self.diff_origin
+        } else {
+            self.focus
+        };
+        match panel {
+            Panel::Staged => self.selected_staged().cloned(),
+            Panel::Unstaged => self.selected_unstaged().cloned(),
+            Panel::Changes => {
+                let entry = self
+                    .changes_list
+                    .selected()
+                    .and_then(|i| self.snapshot.entries.get(i))?;
+                match action {
+                    IndexAction::Stage => entry.unstaged().or_else(|| entry.staged()),
+                    IndexAction::Unstage => entry.staged().or_else(|| entry.unstaged()),
+                }
+            }
+            Panel::Commits
+            | Panel::CommitFiles
+            | Panel::Diff
+            | Panel::Discards
+            | Panel::Branches
+            | Panel::Tags
+            | Panel::Stashes => None,
+        }
+    }
+
+    fn confirm_discard_file(&mut self) {
+        let file = match self.focus {
+            Panel::Unstaged => self.selected_unstaged().cloned(),
+            Panel::Changes => self
+                .changes_list
+                .selected()
+                .and_then(|i| self.snapshot.entries.get(i))
+                .and_then(StatusEntry::unstaged),
+            _ => None,
+        };
+        let Some(file) = file else {
+            self.set_error("Only unstaged changes can be discarded".to_string());
+            return;
+        };
+
+        let prompt = if file.status == FileStatusType::Untracked {
+            format!("Delete untracked {}?", file.display_path())
+        } else {
+            format!("Discard the unstaged changes to {}?", file.display_path())
+        };
+        let paths = file_paths(&file).into_iter().map(Path::to_path_buf).collect();
+        self.confirmation = Some(Confirmation {
+            prompt,
+            action: ConfirmedAction::DiscardPaths(paths),
+        });
+    }
+
+    fn confirm_discard_lines(&mut self) {
+        let Some(view) = self.diff_view.as_ref().filter(|view| !view.is_loading()) else {
+            return;
+        };
+        let Some((file, selected)) = view.selection() else {
+            return;
+        };
+        let file = &view.files()[file];
+        match file.discard_patch(&selected) {
+            Ok(patch) => {
+                let what = if view.has_range() { "lines" } else { "changes" };
+                self.confirmation = Some(Confirmation {
+                    prompt: format!("Discard the selected {} in {}?", what, file.path().display()),
+                    action: ConfirmedAction::DiscardLines {
+                        path: file.path().to_path_buf(),
+                        patch,
+                    },
+                });
+            }
+            Err(e) => self.set_error(format!("Failed to discard: {}", e)),
+        }
+    }
+
+    fn confirm_restore(&mut self) {
+        let Some(record) = self.discards_list.selected().and_then(|i| self.discards.get(i)) else {
+            return;
+        };
+        let paths = record
+            .paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.confirmation = Some(Confirmation {
+            prompt: format!("Restore {}, overwriting the current work tree version?", paths),
+            action: ConfirmedAction::RestoreDiscard(record.id),
+        });
+    }
+
+    fn handle_confirmation_key(&mut self, key: crossterm::event::KeyEvent) {
+        use crossterm::event::KeyCode;
+
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                if let Some(confirmation) = self.confirmation.take() {
+                    self.run_confirmed(confirmation.action);
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc | KeyCode::Char('q') => self.confirmation = None,
+            _ => {}
+        }
+    }
+
+    fn run_confirmed(&mut self, action: ConfirmedAction) {
+        let result = match &action {
+            ConfirmedAction::StashAndCheckout(name) => {
+                let message = format!("gittop: before switching to {}", name);
+                let result = self
+                    .repo
+                    .stash_push(Some(&message), false)
+                    .and_then(|stash| {
+                        // Don't leave the changes in a stash the user never
+                        // sees if the switch fails: put them back.
+                        self.repo.checkout_branch(name).map_err(|e| {
+                            match self.repo.stash_apply(stash, true) {
+                                Ok(()) => e.context("Your changes were left as they were"),
+                                Err(pop) => e.context(format!(
+                                    "Your changes were stashed as stash@{{0}} and could not be restored ({:#})",
+                                    pop
+                                )),
+                            }
+                        })
+                    })
+                    .map(|()| format!("Stashed changes and switched to {}", name));
+                self.operation_done(result);
+                return;
+            }
+            ConfirmedAction::DeleteBranch { name, force } => {
+                let result = self.repo.delete_branch(name, *force);
+                self.operation_done(result.map(|()| format!("Deleted branch {}", name)));
+                return;
+            }
+            ConfirmedAction::DeleteTag(name) => {
+                let result = self.repo.delete_tag(name);
+                self.operation_done(result.map(|()| format!("Deleted tag {}", name)));
+                return;
+            }
+            ConfirmedAction::DropStash(id) => {
+                let result = self.repo.stash_drop(*id);
+                self.operation_done(result.map(|()| "Dropped stash".to_string()));
+                return;
+            }
+            ConfirmedAction::DiscardPaths(paths) => {
+                let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+                self.repo.discard_paths(&paths)
+            }
+            ConfirmedAction::DiscardLines { path, patch } => self.repo.discard_lines(path, patch),
+            ConfirmedAction::RestoreDiscard(id) => self.repo.restore_discard(*id).map(|_| ()),
+        };
+
+        match result {
+            Ok(()) => {
+                self.load_discards();
+                self.reload_diff();
+                self.refresh(RefreshScope {
+                    status: true,
+                    ..RefreshScope::NONE
+                });
+            }
+            Err(e) => {
+                let verb = match action {
+                    ConfirmedAction::RestoreDiscard(_) => "restore",
+                    _ => "discard",
+                };
+                self.set_error(format!("Failed to {}: {}", verb, e));
+            }
+        }
+    }
+
+    fn handle_editor_key(&mut self, key: crossterm::event::KeyEvent) {
+        // Keys wait until the commit being made is done.
+        let Some(editor) = self
+            .commit_editor
+            .as_mut()
+            .filter(|editor| !editor.is_committing())
+        else {
+            return;
+        };
+        match editor.handle_key(key) {
+            EditorAction::None => {}
+            EditorAction::Cancel => {
+                editor.set_error(None);
+                self.commit_draft = self.commit_editor.take();
+            }
+            EditorAction::ToggleAmend => {
+                let amend = !editor.is_amend();
+                editor.set_amend(amend);
+                // Start an amend from the message being amended.
+                if amend && editor.is_empty() {
+                    match self.repo.head_message() {
+                        Ok(message) => editor.set_message(&message),
+                        Err(e) => editor.set_error(Some(format!("Nothing to amend: {}", e))),
+                    }
+                }
+            }
+            EditorAction::Submit => {
+                editor.set_error(None);
+                editor.set_committing(true);
+                self.worker.request_commit(editor.message(), editor.is_amend());
+            }
+        }
+    }
+
+    fn commit_done(&mut self, outcome: CommitOutcome) {
+        match outcome.result {
+            Ok(id) => {
+                let verb = if outcome.amend { "Amended" } else { "Committed" };
+                self.last_change = Some((format!("{} {:.8}", verb, id), Local::now()));
+                self.commit_editor = None;
+                self.refresh(RefreshScope::ALL);
+            }
+            Err(e) => {
+                if let Some(editor) = &mut self.commit_editor {
+                    editor.set_committing(false);
+                    editor.set_error(Some(format!("{:#}", e)));
+                }
+            }
+        }
+    }
+
+    fn load_discards(&mut self) {
+        match self.repo.discards() {
+            Ok(discards) => self.discards = discards,
+            Err(e) => self.set_error(format!("Failed to list discards: {}", e)),
+        }
+        self.discards_list.set_len(self.discards.len());
+    }
+
+    fn selected_staged(&self) -> Option<&FileStatus> {
+        self.staged_list.selected().and_then(|i| self.snapshot.staged.get(i))
+    }
+
+    fn selected_unstaged(&self) -> Option<&FileStatus> {
+        self.unstaged_list.selected().and_then(|i| self.snapshot.unstaged.get(i))
+    }
+
+    fn cycle_focus(&mut self, step: isize) {
+        let panels = self.visible_panels();
+        let current = panels.iter().position(|&p| p == self.focus).unwrap_or(0) as isize;
+        let next = (current + step).rem_euclid(panels.len() as isize) as usize;
+        self.focus = panels[next];
+        self.follow_focus();
+    }
+
+    // The diff pane shows the selection of whichever file panel was focused
+    // last.
+    fn follow_focus(&mut self) {
+        if self.is_file_panel(self.focus) && self.focus != self.diff_origin {
+            self.diff_origin = self.focus;
+            self.reload_diff();
+        }
+    }
+
+    fn clamp_focus(&mut self) {
+        let panels = self.visible_panels();
+        if panels.contains(&self.focus) {
+            return;
+        }
+        self.focus = match self.focus {
+            Panel::Staged | Panel::Unstaged if panels.contains(&Panel::Changes) => Panel::Changes,
+            Panel::Changes if panels.contains(&Panel::Staged) => Panel::Staged,
+            _ => panels[0],
+        };
+    }
+
+    fn sync_selections(&mut self) {
+        self.staged_list.set_len(self.snapshot.staged.len());
+        self.unstaged_list.set_len(self.snapshot.unstaged.len());
+        self.changes_list.set_len(self.snapshot.entries.len());
+        self.branches_list.set_len(self.snapshot.branches.len());
+        self.tags_list.set_len(self.snapshot.tags.len());
+        self.stashes_list.set_len(self.snapshot.stashes.len());
+    }
+
+    pub fn focus(&self) -> Panel {
+        self.focus
+    }
+
+    pub fn list_mut(&mut self, panel: Panel) -> Option<&mut ListSelection> {
+        match panel {
+            Panel::Staged => Some(&mut self.staged_list),
+            Panel::Unstaged => Some(&mut self.unstaged_list),
+            Panel::Changes => Some(&mut self.changes_list),
+            Panel::Commits => Some(&mut self.commits_list),
+            Panel::Discards => Some(&mut self.discards_list),
+            Panel::Branches => Some(&mut self.branches_list),
+            Panel::Tags => Some(&mut self.tags_list),
+            Panel::Stashes => Some(&mut self.stashes_list),
+            Panel::CommitFiles => Some(&mut self.commit_files_list),
+            Panel::Diff => None,
+        }
+    }
+
+    pub fn diff_view_mut(&mut self) -> Option<&mut DiffView> {
+        self.diff_view.as_mut()
+    }
+
     pub fn update(&mut self) -> Result<()> {
         self.last_error = None;
+        self.refresh(RefreshScope::ALL);
         Ok(())
     }
 
+    fn refresh(&mut self, scope: RefreshScope) {
+        if scope.commits {
+            self.reload_history();
+        }
+        self.worker.request_refresh(scope);
+        self.refreshing = true;
+    }
+
+    fn poll_status_if_degraded(&mut self) {
+        let degraded = self.watch_status.is_some_and(|status| status.degraded);
+        if degraded && !self.refreshing && self.last_status_poll.elapsed() >= DEGRADED_POLL_INTERVAL
+        {
+            self.last_status_poll = Instant::now();
+            self.refresh(RefreshScope {
+                status: true,
+                ..RefreshScope::NONE
+            });
+        }
+    }
+
+    fn handle_changes(&mut self, changes: BTreeSet<GitChangeEvent>) {
+        let scope = changes
+            .iter()
+            .fold(RefreshScope::NONE, |scope, change| scope.union(change.refresh_scope()));
+        if scope.is_empty() {
+            return;
+        }
+
+        let description = changes
+            .iter()
+            .map(GitChangeEvent::description)
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.last_change = Some((description, Local::now()));
+        self.refresh(scope);
+    }
+
+    fn poll_worker(&mut self) {
+        if let Some(outcome) = self.worker.try_recv_commit() {
+            self.commit_done(outcome);
+        }
+        if let Some(outcome) = self.worker.try_recv() {
+            match outcome.result {
+                Ok(snapshot) => {
+                    self.snapshot.merge(snapshot, outcome.scope);
+                    tags::sort(&mut self.snapshot.tags, self.tag_order);
+                    self.sync_selections();
+                    let stash_diff = outcome.scope.refs && self.diff_origin == Panel::Stashes;
+                    if outcome.scope.status || stash_diff {
+                        self.reload_diff();
+                    }
+                }
+                Err(e) => self.set_error(format!("Refresh failed: {:#}", e)),
+            }
+            self.refreshing = false;
+        }
+    }
+
     pub fn repo(&self) -> &GitRepository {
         &self.repo
     }
 
+    pub fn snapshot(&self) -> &RepoSnapshot {
+        &self.snapshot
+    }
+
+    pub fn is_refreshing(&self) -> bool {
+        self.refreshing
+    }
+
+    pub fn last_change(&self) -> Option<&(String, DateTime<Local>)> {
+        self.last_change.as_ref()
+    }
+
+    pub fn is_combined_status(&self) -> bool {
+        self.combined_status
+    }
+
     pub fn current_tab(&self) -> &TabType {
         &self.current_tab
     }
@@ -126,4 +1475,114 @@ impl App {
     pub fn set_error(&mut self, error: String) {
         self.last_error = Some(error);
     }
-}
\ No newline at end of file
+
+    pub fn history(&self) -> HistoryScope {
+        self.history
+    }
+
+    pub fn commit_filter(&self) -> Option<&CommitFilter> {
+        self.commit_filter.as_ref()
+    }
+
+    /// Whether the commit at `index` in `commits` matches the filter.
+    pub fn is_match(&self, index: usize) -> bool {
+        self.commit_matches.binary_search(&index).is_ok()
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.commit_matches.len()
+    }
+
+    /// The open prompt's label and what has been typed so far.
+    pub fn prompt(&self) -> Option<(String, &str)> {
+        let prompt = self.prompt.as_ref()?;
+        let label = match &prompt.kind {
+            PromptKind::Search => "/".to_string(),
+            PromptKind::CreateBranch(id) => format!("New branch at {:.8}: ", id),
+            PromptKind::RenameBranch(name) => format!("Rename {} to: ", name),
+            PromptKind::CreateTag(id) => format!("New tag at {:.8}: ", id),
+            PromptKind::TagMessage { name, .. } => {
+                format!("Message for {} (none for a lightweight tag): ", name)
+            }
+            PromptKind::StashPush { include_untracked: false } => "Stash message: ".to_string(),
+            PromptKind::StashPush { include_untracked: true } => {
+                "Stash message (with untracked files): ".to_string()
+            }
+        };
+        Some((label, &prompt.input))
+    }
+
+    pub fn commits(&self) -> &[CommitInfo] {
+        &self.commits
+    }
+
+    pub fn history_loader(&self) -> &HistoryLoader {
+        &self.history_loader
+    }
+
+    pub fn tag_order(&self) -> TagOrder {
+        self.tag_order
+    }
+
+    pub fn commit_detail(&self) -> Option<&CommitDetail> {
+        self.commit_detail.as_ref()
+    }
+
+    pub fn is_loading_commit(&self) -> bool {
+        self.loading_commit
+    }
+
+    pub fn discards(&self) -> &[DiscardRecord] {
+        &self.discards
+    }
+
+    pub fn commit_editor(&self) -> Option<&CommitEditor> {
+        self.commit_editor.as_ref()
+    }
+
+    pub fn confirmation(&self) -> Option<&str> {
+        self.confirmation.as_ref().map(|confirmation| confirmation.prompt.as_str())
+    }
+
+    pub fn watch_status(&self) -> Option<WatchStatus> {
+        self.watch_status
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        let status = self.watch_status?;
+        if status.degraded {
+            Some(format!(
+                "File watch limit reached: watching .git only, polling status every {}s",
+                DEGRADED_POLL_INTERVAL.as_secs()
+            ))
+        } else if let (WatchMode::Native, WatchMode::Poll(interval)) =
+            (self.options.watch_mode, status.mode)
+        {
+            Some(format!(
+                "File watch limit reached; polling every {:.1}s instead",
+                interval.as_secs_f64()
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// The paths a file's change touches; both sides of a rename.
+fn file_paths(file: &FileStatus) -> Vec<&Path> {
+    let mut paths = vec![file.path.as_path()];
+    if let Some(rename) = &file.rename {
+        paths.push(rename.old_path.as_path());
+    }
+    paths
+}
+
+/// The directory holding `path`; untracked directories are listed with a
+/// trailing slash and stand for themselves.
+fn containing_directory(path: &Path) -> PathBuf {
+    if path.to_string_lossy().ends_with('/') {
+        path.to_path_buf()
+    } else {
+        path.parent().map(Path::to_path_buf).unwrap_or_default()
+    }
+}
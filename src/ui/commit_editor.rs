@@ -0,0 +1,320 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Summaries longer than this get a warning colour.
+const SUMMARY_WIDTH: usize = 50;
+/// Body lines are wrapped at this width; anything past it is highlighted.
+const BODY_WIDTH: usize = 72;
+
+/// What a key press in the editor asks the app to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorAction {
+    None,
+    Submit,
+    Cancel,
+    ToggleAmend,
+}
+
+/// The commit message being composed: its lines and a cursor position
+/// (row, and column in characters).
+#[derive(Debug, Clone)]
+pub struct CommitEditor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    amend: bool,
+    error: Option<String>,
+    /// The commit has been handed to the worker and its hooks may be
+    /// running; the message can't be edited until it's done.
+    committing: bool,
+}
+
+impl Default for CommitEditor {
+    fn default() -> Self {
+        Self {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+            amend: false,
+            error: None,
+            committing: false,
+        }
+    }
+}
+
+impl CommitEditor {
+    pub fn message(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|line| line.trim().is_empty())
+    }
+
+    /// Replaces the text, leaving the cursor at the end of the summary.
+    pub fn set_message(&mut self, message: &str) {
+        self.lines = message.trim_end().lines().map(str::to_string).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.row = 0;
+        self.col = char_len(&self.lines[0]);
+    }
+
+    pub fn is_amend(&self) -> bool {
+        self.amend
+    }
+
+    pub fn set_amend(&mut self, amend: bool) {
+        self.amend = amend;
+    }
+
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.error = error;
+    }
+
+    pub fn is_committing(&self) -> bool {
+        self.committing
+    }
+
+    pub fn set_committing(&mut self, committing: bool) {
+        self.committing = committing;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> EditorAction {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return match key.code {
+                KeyCode::Char('s') => EditorAction::Submit,
+                KeyCode::Char('a') => EditorAction::ToggleAmend,
+                _ => EditorAction::None,
+            };
+        }
+
+        match key.code {
+            KeyCode::Esc => return EditorAction::Cancel,
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Enter => self.split_line(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Up if self.row > 0 => {
+                self.row -= 1;
+                self.clamp_col();
+            }
+            KeyCode::Down if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.clamp_col();
+            }
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = char_len(&self.lines[self.row]),
+            _ => {}
+        }
+        EditorAction::None
+    }
+
+    fn insert(&mut self, c: char) {
+        let line = &mut self.lines[self.row];
+        line.insert(byte_offset(line, self.col), c);
+        self.col += 1;
+        if self.row > 0 {
+            self.wrap_line();
+        }
+    }
+
+    // Breaks an overlong body line at its last space within the width,
+    // carrying the cursor along if it was past the break.
+    fn wrap_line(&mut self) {
+        let line = &self.lines[self.row];
+        if char_len(line) <= BODY_WIDTH {
+            return;
+        }
+        let Some(space) = line
+            .char_indices()
+            .take(BODY_WIDTH + 1)
+            .filter(|(_, c)| *c == ' ')
+            .map(|(i, _)| i)
+            .last()
+        else {
+            return;
+        };
+
+        let rest = line[space + 1..].to_string();
+        let break_col = char_len(&line[..space]);
+        self.lines[self.row].truncate(space);
+        self.lines.insert(self.row + 1, rest);
+        if self.col > break_col {
+            self.row += 1;
+            self.col -= break_col + 1;
+        }
+    }
+
+    fn split_line(&mut self) {
+        let line = &mut self.lines[self.row];
+        let rest = line.split_off(byte_offset(line, self.col));
+        self.lines.insert(self.row + 1, rest);
+        self.row += 1;
+        self.col = 0;
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let line = &mut self.lines[self.row];
+            line.remove(byte_offset(line, self.col));
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = char_len(&self.lines[self.row]);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn delete(&mut self) {
+        let len = char_len(&self.lines[self.row]);
+        if self.col < len {
+            let line = &mut self.lines[self.row];
+            line.remove(byte_offset(line, self.col));
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = char_len(&self.lines[self.row]);
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.col < char_len(&self.lines[self.row]) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    fn clamp_col(&mut self) {
+        self.col = self.col.min(char_len(&self.lines[self.row]));
+    }
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+fn byte_offset(s: &str, col: usize) -> usize {
+    s.char_indices().nth(col).map_or(s.len(), |(i, _)| i)
+}
+
+/// Colours the part of a line past `warn` yellow and past `limit` red.
+fn guided_line(line: &str, warn: usize, limit: usize) -> Line<'static> {
+    let head: String = line.chars().take(warn).collect();
+    let middle: String = line.chars().skip(warn).take(limit - warn).collect();
+    let tail: String = line.chars().skip(limit).collect();
+    Line::from(vec![
+        Span::raw(head),
+        Span::styled(middle, Style::default().fg(Color::Yellow)),
+        Span::styled(tail, Style::default().fg(Color::Red)),
+    ])
+}
+
+pub fn draw_commit_editor(f: &mut Frame, area: Rect, editor: &CommitEditor) {
+    let width = (BODY_WIDTH as u16 + 10).min(area.width);
+    let height = (area.height * 2 / 3).max(10).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let title = match (editor.amend, editor.committing) {
+        (false, false) => " Commit ",
+        (true, false) => " Amend HEAD ",
+        (false, true) => " Committing… ",
+        (true, true) => " Amending HEAD… ",
+    };
+    let summary_len = char_len(&editor.lines[0]);
+    let summary_style = match summary_len {
+        n if n > BODY_WIDTH => Style::default().fg(Color::Red),
+        n if n > SUMMARY_WIDTH => Style::default().fg(Color::Yellow),
+        _ => Style::default().fg(Color::Gray),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title(title)
+        .title_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .title_bottom(Line::from(vec![
+            Span::styled(
+                format!(" summary {}/{} ", summary_len, SUMMARY_WIDTH),
+                summary_style,
+            ),
+            Span::raw("─ "),
+            Span::styled("Ctrl+S", Style::default().fg(Color::Yellow)),
+            Span::raw(" commit  "),
+            Span::styled("Ctrl+A", Style::default().fg(Color::Yellow)),
+            Span::raw(" amend  "),
+            Span::styled("Esc", Style::default().fg(Color::Yellow)),
+            Span::raw(" close "),
+        ]));
+
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+
+    let error_height = editor
+        .error
+        .as_ref()
+        .map_or(0, |error| (error.lines().count() as u16 + 1).min(inner.height / 2));
+    let text_area = Rect {
+        height: inner.height - error_height,
+        ..inner
+    };
+
+    let rows = text_area.height.max(1) as usize;
+    let scroll = editor.row.saturating_sub(rows - 1);
+    let lines: Vec<Line> = editor
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                guided_line(line, SUMMARY_WIDTH, BODY_WIDTH)
+            } else {
+                guided_line(line, BODY_WIDTH, BODY_WIDTH)
+            }
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines).scroll((scroll as u16, 0)), text_area);
+
+    if let Some(error) = &editor.error {
+        let error_area = Rect {
+            y: text_area.y + text_area.height,
+            height: error_height,
+            ..inner
+        };
+        f.render_widget(
+            Paragraph::new(error.clone())
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: false }),
+            error_area,
+        );
+    }
+
+    let cursor_x = text_area.x + (editor.col as u16).min(text_area.width.saturating_sub(1));
+    let cursor_y = text_area.y + (editor.row - scroll) as u16;
+    f.set_cursor_position(Position::new(cursor_x, cursor_y));
+}
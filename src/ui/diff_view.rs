@@ -0,0 +1,471 @@
+use std::collections::BTreeSet;
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::git::diff::{DiffHunk, DiffLine, DiffLineKind, DiffSource, FileDiff};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLayout {
+    Unified,
+    SideBySide,
+}
+
+/// The diff pane: the hunks of the selected file, a line cursor and,
+/// optionally, a range of lines anchored at an earlier cursor position.
+pub struct DiffView {
+    files: Vec<FileDiff>,
+    layout: DiffLayout,
+    /// `files` rendered in `layout`, rebuilt only when either changes.
+    rows: Rows,
+    cursor: usize,
+    anchor: Option<usize>,
+    scroll: usize,
+    page_size: usize,
+    /// New content has been asked for and hasn't arrived yet.
+    loading: bool,
+}
+
+/// What a rendered row shows: a file or hunk header, or up to two diff lines
+/// (side by side, a removed line can share its row with an added one).
+#[derive(Debug, Clone, Copy)]
+struct RowTarget {
+    file: usize,
+    hunk: Option<usize>,
+    lines: [Option<usize>; 2],
+}
+
+#[derive(Default)]
+struct Rows {
+    left: Vec<Line<'static>>,
+    right: Vec<Line<'static>>,
+    targets: Vec<RowTarget>,
+}
+
+impl Rows {
+    fn push(&mut self, target: RowTarget, left: Line<'static>, right: Option<Line<'static>>) {
+        self.left.push(left);
+        self.right.extend(right);
+        self.targets.push(target);
+    }
+}
+
+impl DiffView {
+    pub fn new(files: Vec<FileDiff>) -> Self {
+        let rows = unified_rows(&files);
+        Self {
+            files,
+            layout: DiffLayout::Unified,
+            rows,
+            cursor: 0,
+            anchor: None,
+            scroll: 0,
+            page_size: 1,
+            loading: false,
+        }
+    }
+
+    /// Replaces the content, e.g. after a refresh or after staging part of
+    /// it, keeping the cursor where possible. A line range doesn't survive
+    /// the lines moving underneath it.
+    pub fn set_files(&mut self, files: Vec<FileDiff>) {
+        self.files = files;
+        self.rows = self.build_rows();
+        self.anchor = None;
+        self.cursor = self.cursor.min(self.last_row());
+        self.loading = false;
+    }
+
+    /// Marks the content as out of date until `set_files` replaces it;
+    /// lines can't be staged or discarded meanwhile, since they may no
+    /// longer be there.
+    pub fn set_loading(&mut self) {
+        self.loading = true;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    pub fn files(&self) -> &[FileDiff] {
+        &self.files
+    }
+
+    pub fn layout(&self) -> DiffLayout {
+        self.layout
+    }
+
+    pub fn toggle_layout(&mut self) {
+        self.layout = match self.layout {
+            DiffLayout::Unified => DiffLayout::SideBySide,
+            DiffLayout::SideBySide => DiffLayout::Unified,
+        };
+        self.rows = self.build_rows();
+        self.anchor = None;
+        self.cursor = self.cursor.min(self.last_row());
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let target = self.cursor as isize + delta;
+        self.cursor = target.clamp(0, self.last_row() as isize) as usize;
+    }
+
+    pub fn page_down(&mut self) {
+        self.move_cursor(self.page_size as isize);
+    }
+
+    pub fn page_up(&mut self) {
+        self.move_cursor(-(self.page_size as isize));
+    }
+
+    pub fn cursor_to_top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn cursor_to_bottom(&mut self) {
+        self.cursor = self.last_row();
+    }
+
+    /// Moves the cursor to the next (or previous) hunk header.
+    pub fn jump_hunk(&mut self, forward: bool) {
+        let targets = &self.rows.targets;
+        let is_header = |row: &usize| {
+            let target = targets[*row];
+            target.hunk.is_some() && target.lines == [None, None]
+        };
+        let found = if forward {
+            (self.cursor + 1..targets.len()).find(is_header)
+        } else {
+            (0..self.cursor).rev().find(is_header)
+        };
+        if let Some(row) = found {
+            self.cursor = row;
+        }
+    }
+
+    /// Starts a line range at the cursor, or drops the current one.
+    pub fn toggle_range(&mut self) {
+        self.anchor = match self.anchor {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+    }
+
+    pub fn clear_range(&mut self) {
+        self.anchor = None;
+    }
+
+    pub fn has_range(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    /// The changed lines to stage or unstage, as the index of their file and
+    /// (hunk, line) pairs: those in the line range if there is one, otherwise
+    /// the hunk under the cursor, or the whole file from its header.
+    pub fn selection(&self) -> Option<(usize, BTreeSet<(usize, usize)>)> {
+        let targets = &self.rows.targets;
+        let current = *targets.get(self.cursor)?;
+        let file = &self.files[current.file];
+        let is_change = |hunk: usize, line: usize| file.hunks[hunk].lines[line].kind.is_change();
+
+        let selected = match self.anchor {
+            Some(anchor) => targets[anchor.min(self.cursor)..=anchor.max(self.cursor)]
+                .iter()
+                .filter(|target| target.file == current.file)
+                .filter_map(|target| Some((target.hunk?, target.lines)))
+                .flat_map(|(hunk, lines)| lines.into_iter().flatten().map(move |line| (hunk, line)))
+                .filter(|&(hunk, line)| is_change(hunk, line))
+                .collect(),
+            None => file
+                .hunks
+                .iter()
+                .enumerate()
+                .filter(|(hunk, _)| current.hunk.is_none_or(|h| h == *hunk))
+                .flat_map(|(hunk, h)| (0..h.lines.len()).map(move |line| (hunk, line)))
+                .filter(|&(hunk, line)| is_change(hunk, line))
+                .collect(),
+        };
+        Some((current.file, selected))
+    }
+
+    fn last_row(&self) -> usize {
+        self.rows.targets.len().saturating_sub(1)
+    }
+
+    fn build_rows(&self) -> Rows {
+        match self.layout {
+            DiffLayout::Unified => unified_rows(&self.files),
+            DiffLayout::SideBySide => side_by_side_rows(&self.files),
+        }
+    }
+
+    // Keeps the cursor on screen.
+    fn scroll_to_cursor(&mut self) {
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + self.page_size {
+            self.scroll = self.cursor + 1 - self.page_size;
+        }
+    }
+}
+
+pub fn draw_diff(
+    f: &mut Frame,
+    area: Rect,
+    view: &mut DiffView,
+    block: Block<'static>,
+    focused: bool,
+) {
+    let layout_name = match view.layout {
+        DiffLayout::Unified => "unified",
+        DiffLayout::SideBySide => "side-by-side",
+    };
+    let mut title = if view.anchor.is_some() {
+        format!(" Diff ({}, selecting lines) ", layout_name)
+    } else {
+        format!(" Diff ({}) ", layout_name)
+    };
+    if view.loading {
+        title.push_str("loading… ");
+    }
+    let block = block
+        .title(title)
+        .title_style(Style::default().fg(Color::Cyan));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    view.page_size = (inner.height as usize).max(1);
+    view.scroll_to_cursor();
+
+    // Only the rows on screen are copied and highlighted.
+    let visible = view.scroll..(view.scroll + view.page_size).min(view.rows.left.len());
+    let mut left = view.rows.left.get(visible.clone()).unwrap_or_default().to_vec();
+    let mut right = view.rows.right.get(visible.clone()).unwrap_or_default().to_vec();
+    if focused {
+        let range = view.anchor.map(|anchor| anchor.min(view.cursor)..=anchor.max(view.cursor));
+        for (i, line) in visible.zip(left.iter_mut()) {
+            if i == view.cursor {
+                line.style = line.style.add_modifier(Modifier::REVERSED);
+            } else if range.as_ref().is_some_and(|range| range.contains(&i)) {
+                line.style = line.style.bg(Color::DarkGray);
+            }
+            if let Some(right) = right.get_mut(i - view.scroll) {
+                right.style = line.style;
+            }
+        }
+    }
+
+    match view.layout {
+        DiffLayout::Unified => {
+            f.render_widget(Paragraph::new(left), inner);
+        }
+        DiffLayout::SideBySide => {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(inner);
+            f.render_widget(Paragraph::new(left), columns[0]);
+            f.render_widget(Paragraph::new(right), columns[1]);
+        }
+    }
+}
+
+fn file_header(file: &FileDiff) -> Line<'static> {
+    let source = match file.source {
+        DiffSource::Staged => "staged".to_string(),
+        DiffSource::Unstaged => "unstaged".to_string(),
+        DiffSource::Commit(id) => format!("{:.8}", id),
+        DiffSource::Stash(id) => format!("stash {:.8}", id),
+    };
+    let path = match (&file.old_path, &file.new_path) {
+        (Some(old), Some(new)) if old != new => format!("{} → {}", old.display(), new.display()),
+        _ => file.path().display().to_string(),
+    };
+    Line::from(Span::styled(
+        format!("{}: {}", source, path),
+        Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+    ))
+}
+
+fn file_notice(file: &FileDiff) -> Option<Line<'static>> {
+    let notice = if file.binary {
+        "Binary file differs"
+    } else if file.hunks.is_empty() {
+        "No content changes"
+    } else {
+        return None;
+    };
+    Some(Line::from(Span::styled(notice, Style::default().fg(Color::Gray))))
+}
+
+fn hunk_header(hunk: &DiffHunk) -> Line<'static> {
+    Line::from(Span::styled(hunk.header.clone(), Style::default().fg(Color::Cyan)))
+}
+
+fn line_style(kind: DiffLineKind) -> Style {
+    match kind {
+        DiffLineKind::Added => Style::default().fg(Color::Green),
+        DiffLineKind::Removed => Style::default().fg(Color::Red),
+        DiffLineKind::NoNewline => Style::default().fg(Color::Gray),
+        DiffLineKind::Context => Style::default(),
+    }
+}
+
+fn lineno(number: Option<u32>) -> String {
+    number.map_or_else(|| "    ".to_string(), |n| format!("{:>4}", n))
+}
+
+fn display_content(line: &DiffLine) -> String {
+    line.content.replace('\t', "    ")
+}
+
+fn unified_rows(files: &[FileDiff]) -> Rows {
+    let mut rows = Rows::default();
+
+    for (file_idx, file) in files.iter().enumerate() {
+        let header = RowTarget {
+            file: file_idx,
+            hunk: None,
+            lines: [None, None],
+        };
+        rows.push(header, file_header(file), None);
+        if let Some(notice) = file_notice(file) {
+            rows.push(header, notice, None);
+        }
+
+        for (hunk_idx, hunk) in file.hunks.iter().enumerate() {
+            let hunk_target = RowTarget {
+                hunk: Some(hunk_idx),
+                ..header
+            };
+            rows.push(hunk_target, hunk_header(hunk), None);
+            for (line_idx, line) in hunk.lines.iter().enumerate() {
+                let marker = match line.kind {
+                    DiffLineKind::Added => "+",
+                    DiffLineKind::Removed => "-",
+                    DiffLineKind::NoNewline => "",
+                    DiffLineKind::Context => " ",
+                };
+                let target = RowTarget {
+                    lines: [Some(line_idx), None],
+                    ..hunk_target
+                };
+                let row = Line::from(vec![
+                    Span::styled(
+                        format!("{} {} ", lineno(line.old_lineno), lineno(line.new_lineno)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!("{}{}", marker, display_content(line)),
+                        line_style(line.kind),
+                    ),
+                ]);
+                rows.push(target, row, None);
+            }
+        }
+    }
+
+    rows
+}
+
+fn side_row(line: Option<&DiffLine>, new_side: bool) -> Line<'static> {
+    let Some(line) = line else {
+        return Line::default();
+    };
+    let number = if new_side {
+        line.new_lineno
+    } else {
+        line.old_lineno
+    };
+    Line::from(vec![
+        Span::styled(
+            format!("{} ", lineno(number)),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::styled(display_content(line), line_style(line.kind)),
+    ])
+}
+
+/// Lays the old version out on the left and the new one on the right,
+/// pairing each run of removed lines with the added lines that follow it.
+fn side_by_side_rows(files: &[FileDiff]) -> Rows {
+    let mut rows = Rows::default();
+
+    for (file_idx, file) in files.iter().enumerate() {
+        let header = RowTarget {
+            file: file_idx,
+            hunk: None,
+            lines: [None, None],
+        };
+        rows.push(header, file_header(file), Some(Line::default()));
+        if let Some(notice) = file_notice(file) {
+            rows.push(header, notice, Some(Line::default()));
+        }
+
+        for (hunk_idx, hunk) in file.hunks.iter().enumerate() {
+            let hunk_target = RowTarget {
+                hunk: Some(hunk_idx),
+                ..header
+            };
+            rows.push(hunk_target, hunk_header(hunk), Some(Line::default()));
+
+            let mut removed: Vec<(usize, &DiffLine)> = Vec::new();
+            let mut added: Vec<(usize, &DiffLine)> = Vec::new();
+
+            for (line_idx, line) in hunk.lines.iter().enumerate() {
+                match line.kind {
+                    DiffLineKind::Removed => {
+                        if !added.is_empty() {
+                            flush_pairs(&mut removed, &mut added, hunk_target, &mut rows);
+                        }
+                        removed.push((line_idx, line));
+                    }
+                    DiffLineKind::Added => added.push((line_idx, line)),
+                    DiffLineKind::Context | DiffLineKind::NoNewline => {
+                        flush_pairs(&mut removed, &mut added, hunk_target, &mut rows);
+                        let target = RowTarget {
+                            lines: [Some(line_idx), None],
+                            ..hunk_target
+                        };
+                        rows.push(
+                            target,
+                            side_row(Some(line), false),
+                            Some(side_row(Some(line), true)),
+                        );
+                    }
+                }
+            }
+            flush_pairs(&mut removed, &mut added, hunk_target, &mut rows);
+        }
+    }
+
+    rows
+}
+
+fn flush_pairs(
+    removed: &mut Vec<(usize, &DiffLine)>,
+    added: &mut Vec<(usize, &DiffLine)>,
+    hunk_target: RowTarget,
+    rows: &mut Rows,
+) {
+    for i in 0..removed.len().max(added.len()) {
+        let old = removed.get(i).copied();
+        let new = added.get(i).copied();
+        let target = RowTarget {
+            lines: [old.map(|(idx, _)| idx), new.map(|(idx, _)| idx)],
+            ..hunk_target
+        };
+        rows.push(
+            target,
+            side_row(old.map(|(_, line)| line), false),
+            Some(side_row(new.map(|(_, line)| line), true)),
+        );
+    }
+    removed.clear();
+    added.clear();
+}
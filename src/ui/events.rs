@@ -1,31 +1,88 @@
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use anyhow::Result;
-use std::time::Duration;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use crate::watcher::{FileSystemWatcher, GitChangeEvent, WatchStatus};
+
+/// The longest a change waits to be reported while the tree never goes quiet,
+/// e.g. with a log file that is written to continuously.
+const MAX_DEBOUNCE_WAIT: Duration = Duration::from_secs(2);
 
 pub enum AppEvent {
     KeyPress(KeyEvent),
-    FileSystemChange,
+    FileSystemChange(BTreeSet<GitChangeEvent>),
     Tick,
     Quit,
 }
 
 pub struct EventHandler {
     tick_rate: Duration,
+    watcher: Option<FileSystemWatcher>,
+    debounce: Duration,
+    pending_since: Option<Instant>,
+    // When the oldest change still waiting to be reported came in.
+    first_pending: Option<Instant>,
+    pending_changes: BTreeSet<GitChangeEvent>,
 }
 
 impl EventHandler {
     pub fn new(tick_rate: Duration) -> Self {
-        Self { tick_rate }
+        Self {
+            tick_rate,
+            watcher: None,
+            debounce: Duration::from_millis(300),
+            pending_since: None,
+            first_pending: None,
+            pending_changes: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_watcher(mut self, watcher: FileSystemWatcher, debounce: Duration) -> Self {
+        self.watcher = Some(watcher);
+        self.debounce = debounce;
+        self
     }
 
-    pub fn next(&self) -> Result<AppEvent> {
+    pub fn next_event(&mut self) -> Result<AppEvent> {
         if crossterm::event::poll(self.tick_rate)? {
-            match crossterm::event::read()? {
-                Event::Key(key) => Ok(AppEvent::KeyPress(key)),
-                _ => Ok(AppEvent::Tick),
+            if let Event::Key(key) = crossterm::event::read()? {
+                return Ok(AppEvent::KeyPress(key));
             }
+        }
+
+        Ok(self.poll_watcher())
+    }
+
+    pub fn watch_status(&self) -> Option<WatchStatus> {
+        self.watcher.as_ref().map(FileSystemWatcher::status)
+    }
+
+    // Bursts of notify events (a checkout, a build, an editor's atomic save)
+    // are coalesced: the change is only reported once the tree has been quiet
+    // for the debounce window, or at the latest `MAX_DEBOUNCE_WAIT` after the
+    // first change of the burst.
+    fn poll_watcher(&mut self) -> AppEvent {
+        let Some(watcher) = &mut self.watcher else {
+            return AppEvent::Tick;
+        };
+
+        let changes = watcher.drain_changes();
+        if !changes.is_empty() {
+            self.pending_changes.extend(changes);
+            let now = Instant::now();
+            self.pending_since = Some(now);
+            self.first_pending.get_or_insert(now);
+        }
+
+        let quiet = self.pending_since.is_some_and(|since| since.elapsed() >= self.debounce);
+        let overdue = self.first_pending.is_some_and(|first| first.elapsed() >= MAX_DEBOUNCE_WAIT);
+        if quiet || overdue {
+            self.pending_since = None;
+            self.first_pending = None;
+            AppEvent::FileSystemChange(std::mem::take(&mut self.pending_changes))
         } else {
-            Ok(AppEvent::Tick)
+            AppEvent::Tick
         }
     }
 }
@@ -54,4 +111,4 @@ pub fn should_refresh(key: &KeyEvent) -> bool {
             ..
         }
     )
-}
\ No newline at end of file
+}
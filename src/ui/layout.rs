@@ -1,16 +1,27 @@
 use anyhow::Result;
+use chrono::Local;
+use git2::RepositoryState;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
     Frame,
 };
 
-use crate::git::status::FileStatusType;
-use crate::ui::app::{App, TabType};
+use crate::git::graph::GraphCell;
+use crate::git::repository::HistoryScope;
+use crate::git::status::{FileStatus, FileStatusType, LineStats, Person};
+use crate::git::{OperationState, RepoSnapshot, TagOrder};
+use crate::ui::app::{App, Panel, TabType};
+use crate::ui::commit_editor::draw_commit_editor;
+use crate::ui::diff_view::draw_diff;
+use crate::watcher::WatchMode;
 
-pub fn draw_ui(f: &mut Frame, app: &App) -> Result<()> {
+pub fn draw_ui(f: &mut Frame, app: &mut App) -> Result<()> {
     let size = f.area();
     
     // Main layout
@@ -26,14 +37,19 @@ pub fn draw_ui(f: &mut Frame, app: &App) -> Result<()> {
 
     draw_header(f, chunks[0], app)?;
     draw_content(f, chunks[1], app)?;
-    draw_footer(f, chunks[2]);
+    draw_footer(f, chunks[2], app);
+    if let Some(editor) = app.commit_editor() {
+        draw_commit_editor(f, size, editor);
+    }
+    if let Some(prompt) = app.confirmation() {
+        draw_confirmation(f, size, prompt);
+    }
 
     Ok(())
 }
 
 fn draw_header(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
-    let branch = app.repo().current_branch().unwrap_or_else(|_| "Unknown".to_string());
-    let remote_status = app.repo().remote_status().ok();
+    let snapshot = app.snapshot();
     let path = app.repo().path().display().to_string();
     
     let mut header_text = vec![
@@ -42,10 +58,18 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
         Span::styled(&path, Style::default().fg(Color::White)),
         Span::raw(" - "),
         Span::styled("Branch: ", Style::default().fg(Color::Gray)),
-        Span::styled(&branch, Style::default().fg(Color::Green)),
+        Span::styled(branch_name(snapshot), Style::default().fg(Color::Green)),
     ];
 
-    if let Some(remote) = remote_status {
+    if let Some(operation) = &snapshot.operation {
+        header_text.push(Span::raw(" - "));
+        header_text.push(Span::styled(
+            operation.label(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let Some(remote) = &snapshot.remote {
         if remote.ahead > 0 || remote.behind > 0 {
             header_text.push(Span::raw(" - "));
             header_text.push(Span::styled(
@@ -55,7 +79,34 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
         }
     }
 
-    let header = Paragraph::new(Line::from(header_text))
+    if let Some((change, at)) = app.last_change() {
+        header_text.push(Span::raw(" - "));
+        header_text.push(Span::styled(
+            format!("{} at {}", change, at.format("%H:%M:%S")),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+
+    if app.is_refreshing() {
+        header_text.push(Span::raw(" - "));
+        header_text.push(Span::styled(
+            "refreshing…",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
+        ));
+    }
+
+    let mut tabs = vec![Span::raw(" ")];
+    for tab in TabType::ALL {
+        let style = if tab == *app.current_tab() {
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        tabs.push(Span::styled(format!(" {} ", tab.title()), style));
+        tabs.push(Span::raw(" "));
+    }
+
+    let header = Paragraph::new(vec![Line::from(header_text), Line::from(tabs)])
         .style(Style::default().bg(Color::DarkGray))
         .block(Block::default().borders(Borders::BOTTOM));
 
@@ -63,7 +114,7 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
     Ok(())
 }
 
-fn draw_content(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
+fn draw_content(f: &mut Frame, area: Rect, app: &mut App) -> Result<()> {
     let content_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
@@ -72,17 +123,121 @@ fn draw_content(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
     match app.current_tab() {
         TabType::Status => {
             draw_status_view(f, content_chunks[0], app)?;
-            draw_recent_commits(f, content_chunks[1], app)?;
+            if app.visible_panels().contains(&Panel::Diff) {
+                let block = panel_block(app, Panel::Diff);
+                let focused = app.focus() == Panel::Diff;
+                if let Some(view) = app.diff_view_mut() {
+                    draw_diff(f, content_chunks[1], view, block, focused);
+                }
+            } else {
+                draw_recent_commits(f, content_chunks[1], app)?;
+            }
         }
-        TabType::Commits => {
+        TabType::Commits if app.commit_detail().is_none() => {
             draw_recent_commits(f, area, app)?;
         }
+        TabType::Commits => {
+            if app.visible_panels().contains(&Panel::Diff) {
+                draw_commit_detail(f, content_chunks[0], app);
+                let block = panel_block(app, Panel::Diff);
+                let focused = app.focus() == Panel::Diff;
+                if let Some(view) = app.diff_view_mut() {
+                    draw_diff(f, content_chunks[1], view, block, focused);
+                }
+            } else {
+                draw_recent_commits(f, content_chunks[0], app)?;
+                draw_commit_detail(f, content_chunks[1], app);
+            }
+        }
+        TabType::Branches => draw_branches(f, area, app),
+        TabType::Tags => draw_tags(f, area, app),
+        TabType::Stash if app.visible_panels().contains(&Panel::Diff) => {
+            draw_stashes(f, content_chunks[0], app);
+            let block = panel_block(app, Panel::Diff);
+            let focused = app.focus() == Panel::Diff;
+            if let Some(view) = app.diff_view_mut() {
+                draw_diff(f, content_chunks[1], view, block, focused);
+            }
+        }
+        TabType::Stash => draw_stashes(f, area, app),
+        TabType::Discards => draw_discards(f, area, app),
     }
 
     Ok(())
 }
 
-fn draw_status_view(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
+// During a rebase HEAD is detached; the branch being rebased is the one
+// that matters.
+fn branch_name(snapshot: &RepoSnapshot) -> &str {
+    snapshot
+        .operation
+        .as_ref()
+        .and_then(|operation| operation.rebase_branch.as_deref())
+        .unwrap_or(snapshot.branch_name())
+}
+
+/// The operation under way, spelled out for the status summary.
+fn operation_lines(operation: &OperationState) -> Vec<Line<'static>> {
+    const SHOWN_STEPS: usize = 5;
+
+    let highlight = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    let detail = Style::default().fg(Color::Yellow);
+    let mut lines = Vec::new();
+
+    let verb = match operation.state {
+        RepositoryState::Merge => "Merging",
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "Cherry-picking",
+        RepositoryState::Revert | RepositoryState::RevertSequence => "Reverting",
+        RepositoryState::Bisect => "Bisecting",
+        RepositoryState::ApplyMailbox => "Applying patches",
+        _ => "Rebasing",
+    };
+    let mut heading = vec![Span::styled(verb, highlight)];
+    if let Some(branch) = &operation.rebase_branch {
+        heading.push(Span::raw(format!(" {}", branch)));
+    }
+    if let Some(onto) = &operation.onto {
+        heading.push(Span::raw(" onto "));
+        heading.push(Span::styled(onto.clone(), detail));
+    }
+    if let Some((step, total)) = operation.progress {
+        heading.push(Span::raw(format!(" (step {} of {})", step, total)));
+    }
+    lines.push(Line::from(heading));
+
+    for head in &operation.heads {
+        lines.push(Line::from(vec![Span::raw("  "), Span::styled(head.clone(), detail)]));
+    }
+    if let Some(subject) = operation.message.as_deref().and_then(|message| message.lines().next()) {
+        lines.push(Line::from(format!("  Message: {}", subject)));
+    }
+
+    if !operation.todo.is_empty() {
+        lines.push(Line::from(format!("  Next ({} to go):", operation.todo.len())));
+        for step in operation.todo.iter().take(SHOWN_STEPS) {
+            lines.push(Line::from(format!("    {}", step)));
+        }
+        if operation.todo.len() > SHOWN_STEPS {
+            lines.push(Line::from(format!("    … {} more", operation.todo.len() - SHOWN_STEPS)));
+        }
+    }
+
+    if let Some(bad) = &operation.bisect_bad {
+        lines.push(Line::from(vec![
+            Span::styled("  bad  ", Style::default().fg(Color::Red)),
+            Span::raw(bad.clone()),
+        ]));
+    }
+    for good in &operation.bisect_good {
+        lines.push(Line::from(vec![
+            Span::styled("  good ", Style::default().fg(Color::Green)),
+            Span::raw(good.clone()),
+        ]));
+    }
+    lines
+}
+
+fn draw_status_view(f: &mut Frame, area: Rect, app: &mut App) -> Result<()> {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -92,60 +247,27 @@ fn draw_status_view(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
         ])
         .split(area);
 
-    // Staged changes
-    let staged_files = app.repo().staged_files().unwrap_or_default();
-    let staged_items: Vec<ListItem> = staged_files
-        .iter()
-        .map(|file| {
-            let symbol = get_status_symbol(&file.status);
-            let color = get_status_color(&file.status);
-            ListItem::new(format!("{} {}", symbol, file.path.display()))
-                .style(Style::default().fg(color))
-        })
-        .collect();
-
-    let staged_list = List::new(staged_items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!(" Staged Changes ({}) ", staged_files.len()))
-                .title_style(Style::default().fg(Color::Green)),
-        )
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD));
-
-    f.render_widget(staged_list, chunks[0]);
-
-    // Unstaged changes
-    let unstaged_files = app.repo().unstaged_files().unwrap_or_default();
-    let unstaged_items: Vec<ListItem> = unstaged_files
-        .iter()
-        .map(|file| {
-            let symbol = get_status_symbol(&file.status);
-            let color = get_status_color(&file.status);
-            ListItem::new(format!("{} {}", symbol, file.path.display()))
-                .style(Style::default().fg(color))
-        })
-        .collect();
-
-    let unstaged_list = List::new(unstaged_items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!(" Unstaged Changes ({}) ", unstaged_files.len()))
-                .title_style(Style::default().fg(Color::Yellow)),
-        );
+    if app.is_combined_status() {
+        let combined_area = Rect {
+            height: chunks[0].height + chunks[1].height,
+            ..chunks[0]
+        };
+        draw_combined_status(f, combined_area, app);
+    } else {
+        draw_split_status(f, chunks[0], chunks[1], app);
+    }
 
-    f.render_widget(unstaged_list, chunks[1]);
+    let snapshot = app.snapshot();
 
     // Status summary
-    let branch = app.repo().current_branch().unwrap_or_else(|_| "Unknown".to_string());
-    let remote_status = app.repo().remote_status().ok();
-    
     let mut status_text = vec![
-        Line::from(format!("On branch: {}", branch)),
+        Line::from(format!("On branch: {}", branch_name(snapshot))),
     ];
+    if let Some(operation) = &snapshot.operation {
+        status_text.extend(operation_lines(operation));
+    }
 
-    if let Some(remote) = remote_status {
+    if let Some(remote) = &snapshot.remote {
         if remote.is_up_to_date() {
             status_text.push(Line::from("Your branch is up to date"));
         } else {
@@ -164,6 +286,22 @@ fn draw_status_view(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
         }
     }
 
+    let total = snapshot.staged_totals() + snapshot.unstaged_totals();
+    let mut totals_line = vec![Span::raw("Uncommitted: ")];
+    totals_line.extend(line_stats_spans(total));
+    status_text.push(Line::from(totals_line));
+
+    if let Some(watch) = app.watch_status() {
+        let mode = match watch.mode {
+            WatchMode::Native => "notifications".to_string(),
+            WatchMode::Poll(interval) => format!("polling every {:.1}s", interval.as_secs_f64()),
+        };
+        status_text.push(Line::from(Span::styled(
+            format!("Watching {} directories ({})", watch.watches, mode),
+            Style::default().fg(Color::Gray),
+        )));
+    }
+
     let status_summary = Paragraph::new(status_text)
         .block(
             Block::default()
@@ -171,58 +309,710 @@ fn draw_status_view(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
                 .title(" Status ")
                 .title_style(Style::default().fg(Color::Cyan)),
         )
-        .wrap(Wrap { trim: true });
+        .wrap(Wrap { trim: false });
 
     f.render_widget(status_summary, chunks[2]);
 
     Ok(())
 }
 
-fn draw_recent_commits(f: &mut Frame, area: Rect, app: &App) -> Result<()> {
-    let commits = app.repo().recent_commits(10).unwrap_or_default();
-    
-    let commit_items: Vec<ListItem> = commits
+fn draw_split_status(f: &mut Frame, staged_area: Rect, unstaged_area: Rect, app: &mut App) {
+    let snapshot = app.snapshot();
+
+    // Staged changes
+    let staged_files = &snapshot.staged;
+    let staged_items: Vec<ListItem> = staged_files
+        .iter()
+        .map(file_status_item)
+        .collect();
+
+    let staged_list = List::new(staged_items).block(
+        panel_block(app, Panel::Staged)
+            .title(format!(" Staged Changes ({}) ", staged_files.len()))
+            .title_style(Style::default().fg(Color::Green))
+            .title_bottom(line_stats_line(snapshot.staged_totals())),
+    );
+
+    // Unstaged changes
+    let unstaged_files = &snapshot.unstaged;
+    let unstaged_items: Vec<ListItem> = unstaged_files
+        .iter()
+        .map(file_status_item)
+        .collect();
+
+    let unstaged_list = List::new(unstaged_items).block(
+        panel_block(app, Panel::Unstaged)
+            .title(format!(" Unstaged Changes ({}) ", unstaged_files.len()))
+            .title_style(Style::default().fg(Color::Yellow))
+            .title_bottom(line_stats_line(snapshot.unstaged_totals())),
+    );
+
+    draw_list(f, staged_area, app, Panel::Staged, staged_list, 1);
+    draw_list(f, unstaged_area, app, Panel::Unstaged, unstaged_list, 1);
+}
+
+fn panel_block(app: &App, panel: Panel) -> Block<'static> {
+    let border_color = if app.focus() == panel {
+        Color::Cyan
+    } else {
+        Color::Reset
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border_color))
+}
+
+/// Renders a list panel with its selection cursor and, when the items don't
+/// fit, a scrollbar. `item_height` is the number of lines per item.
+fn draw_list(
+    f: &mut Frame,
+    area: Rect,
+    app: &mut App,
+    panel: Panel,
+    list: List<'static>,
+    item_height: usize,
+) {
+    draw_list_window(f, area, app, panel, list, item_height, None);
+}
+
+/// Like `draw_list`; with `first`, the list holds just the items in
+/// `ListSelection::visible_range`, starting at item `first`.
+fn draw_list_window(
+    f: &mut Frame,
+    area: Rect,
+    app: &mut App,
+    panel: Panel,
+    list: List<'static>,
+    item_height: usize,
+    first: Option<usize>,
+) {
+    let focused = app.focus() == panel;
+    let Some(selection) = app.list_mut(panel) else {
+        return;
+    };
+    let visible_items = visible_items(area, item_height);
+    selection.set_page_size(visible_items);
+
+    let list = if focused {
+        list.highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ")
+    } else {
+        list
+    };
+    match first {
+        Some(first) => {
+            let selected = selection.selected().map(|i| i - first);
+            f.render_stateful_widget(list, area, &mut ListState::default().with_selected(selected));
+        }
+        None => f.render_stateful_widget(list, area, selection.state_mut()),
+    }
+
+    if selection.len() > visible_items {
+        let mut scrollbar_state =
+            ScrollbarState::new(selection.len()).position(selection.selected().unwrap_or(0));
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None),
+            area.inner(ratatui::layout::Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+    }
+}
+
+// How many items of `item_height` lines fit in a bordered panel.
+fn visible_items(area: Rect, item_height: usize) -> usize {
+    area.height.saturating_sub(2) as usize / item_height
+}
+
+fn file_status_item(file: &FileStatus) -> ListItem<'static> {
+    let color = get_status_color(&file.status);
+    let mut spans = vec![Span::styled(
+        format!("{} {}", get_status_symbol(&file.status), file.display_path()),
+        Style::default().fg(color),
+    )];
+
+    if let Some(stats) = file.stats {
+        spans.push(Span::raw(" "));
+        spans.extend(line_stats_spans(stats));
+    }
+
+    ListItem::new(Line::from(spans))
+}
+
+fn line_stats_spans(stats: LineStats) -> Vec<Span<'static>> {
+    if stats.binary {
+        return vec![Span::styled("binary", Style::default().fg(Color::Gray))];
+    }
+    vec![
+        Span::styled(format!("+{}", stats.added), Style::default().fg(Color::Green)),
+        Span::raw(" "),
+        Span::styled(format!("-{}", stats.removed), Style::default().fg(Color::Red)),
+    ]
+}
+
+fn line_stats_line(stats: LineStats) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ")];
+    spans.extend(line_stats_spans(stats));
+    spans.push(Span::raw(" "));
+    Line::from(spans)
+}
+
+fn draw_combined_status(f: &mut Frame, area: Rect, app: &mut App) {
+    let entries = &app.snapshot().entries;
+    let column = |code: char, status: Option<&FileStatusType>| {
+        let color = status.map_or(Color::Gray, get_status_color);
+        Span::styled(code.to_string(), Style::default().fg(color))
+    };
+
+    let items: Vec<ListItem> = entries
         .iter()
-        .map(|commit| {
+        .map(|entry| {
+            let code: Vec<char> = entry.short_code().chars().collect();
+            ListItem::new(Line::from(vec![
+                column(code[0], entry.index.as_ref().or(entry.worktree.as_ref())),
+                column(code[1], entry.worktree.as_ref()),
+                Span::raw(" "),
+                Span::raw(entry.display_path()),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        panel_block(app, Panel::Changes)
+            .title(format!(" Changes ({}) - index | work tree ", entries.len()))
+            .title_style(Style::default().fg(Color::Green)),
+    );
+
+    draw_list(f, area, app, Panel::Changes, list, 1);
+}
+
+const LANE_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Red,
+];
+
+fn draw_recent_commits(f: &mut Frame, area: Rect, app: &mut App) -> Result<()> {
+    // The Commits tab shows the full graph; the Status tab just a summary.
+    // With a search, the commits that don't match are dimmed.
+    let decorated = *app.current_tab() == TabType::Commits;
+    let searching = app.commit_filter().is_some();
+    // The history can run to hundreds of thousands of commits; build only
+    // the ones in view.
+    let visible = visible_items(area, 2);
+    let range = match app.list_mut(Panel::Commits) {
+        Some(selection) => {
+            selection.set_page_size(visible);
+            selection.visible_range()
+        }
+        None => 0..0,
+    };
+    let first = range.start;
+    let commit_items: Vec<ListItem> = range
+        .map(|i| {
+            let commit = &app.commits()[i];
+            let dimmed = searching && !app.is_match(i);
+            let color = |color| if dimmed { Color::DarkGray } else { color };
             let time_str = commit.timestamp.format("%Y-%m-%d %H:%M").to_string();
-            ListItem::new(vec![
-                Line::from(vec![
-                    Span::styled(&commit.short_hash, Style::default().fg(Color::Yellow)),
-                    Span::raw(" "),
-                    Span::raw(&commit.message),
-                ]),
-                Line::from(vec![
-                    Span::raw("  "),
-                    Span::styled(&commit.author, Style::default().fg(Color::Green)),
-                    Span::raw(" - "),
-                    Span::styled(time_str, Style::default().fg(Color::Gray)),
-                ]),
-            ])
+            let mut first = Vec::new();
+            let mut second = vec![Span::raw("  ")];
+            if decorated {
+                let width = commit.graph.commit.len().max(commit.graph.edges.len());
+                first = graph_spans(&commit.graph.commit, width);
+                second = graph_spans(&commit.graph.edges, width);
+            }
+            first.push(Span::styled(
+                commit.short_hash.clone(),
+                Style::default().fg(color(Color::Yellow)),
+            ));
+            first.push(Span::raw(" "));
+            if decorated && !commit.refs.is_empty() {
+                first.extend(decoration_spans(&commit.refs));
+                first.push(Span::raw(" "));
+            }
+            let message = match (searching, dimmed) {
+                (_, true) => Style::default().fg(Color::DarkGray),
+                (true, false) => Style::default().add_modifier(Modifier::BOLD),
+                (false, false) => Style::default(),
+            };
+            first.push(Span::styled(commit.message.clone(), message));
+            second.push(Span::styled(commit.author.clone(), Style::default().fg(color(Color::Green))));
+            second.push(Span::raw(" - "));
+            second.push(Span::styled(time_str, Style::default().fg(color(Color::Gray))));
+            ListItem::new(vec![Line::from(first), Line::from(second)])
+        })
+        .collect();
+
+    let loaded = app.commits().len();
+    let loader = app.history_loader();
+    let total = match loader.total() {
+        Some(total) => total.to_string(),
+        None => "…".to_string(),
+    };
+    let mut title = match (decorated, app.history()) {
+        (false, _) => " Recent Commits".to_string(),
+        (true, HistoryScope::Head) => " Commits (HEAD)".to_string(),
+        (true, HistoryScope::AllRefs) => " Commits (all refs)".to_string(),
+    };
+    title.push_str(&format!(" {}/{} ", loaded, total));
+    if let Some(filter) = app.commit_filter() {
+        title.push_str(&format!("matching \"{}\": {} found ", filter.query(), app.match_count()));
+    }
+    if loader.is_loading() {
+        title.push_str(if searching { "searching… " } else { "loading… " });
+    }
+    let commits_list = List::new(commit_items).block(
+        panel_block(app, Panel::Commits)
+            .title(title)
+            .title_style(Style::default().fg(Color::Magenta)),
+    );
+
+    draw_list_window(f, area, app, Panel::Commits, commits_list, 2, Some(first));
+    Ok(())
+}
+
+/// A graph row, padded to `width` cells plus a space, in its lanes' colours.
+fn graph_spans(cells: &[GraphCell], width: usize) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span> = cells
+        .iter()
+        .map(|cell| {
+            let color = LANE_COLORS[cell.lane % LANE_COLORS.len()];
+            Span::styled(cell.glyph.to_string(), Style::default().fg(color))
+        })
+        .collect();
+    spans.push(Span::raw(" ".repeat(width - cells.len() + 1)));
+    spans
+}
+
+/// Ref names the way `git log --decorate` shows them: `(HEAD -> main, tag: v1)`.
+fn decoration_spans(refs: &[String]) -> Vec<Span<'static>> {
+    let mut spans = vec![Span::styled("(", Style::default().fg(Color::Yellow))];
+    for (i, name) in refs.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(", ", Style::default().fg(Color::Yellow)));
+        }
+        let style = if name.starts_with("HEAD") {
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+        } else if name.starts_with("tag: ") {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        spans.push(Span::styled(name.clone(), style));
+    }
+    spans.push(Span::styled(")", Style::default().fg(Color::Yellow)));
+    spans
+}
+
+/// The selected commit's metadata and message above the files it changed.
+fn draw_commit_detail(f: &mut Frame, area: Rect, app: &mut App) {
+    let Some(detail) = app.commit_detail() else {
+        return;
+    };
+    let label = |text: &str| Span::styled(format!("{:<10}", text), Style::default().fg(Color::Gray));
+
+    let mut lines = vec![Line::from(vec![
+        label("Commit"),
+        Span::styled(detail.id.to_string(), Style::default().fg(Color::Yellow)),
+    ])];
+    if !detail.refs.is_empty() {
+        lines.push(Line::from(vec![
+            label("Refs"),
+            Span::styled(detail.refs.join(", "), Style::default().fg(Color::Cyan)),
+        ]));
+    }
+    if !detail.parents.is_empty() {
+        let parents = detail
+            .parents
+            .iter()
+            .map(|id| format!("{:.8}", id))
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(Line::from(vec![
+            label("Parents"),
+            Span::styled(parents, Style::default().fg(Color::Yellow)),
+        ]));
+    }
+    lines.push(Line::from([vec![label("Author")], person_spans(&detail.author)].concat()));
+    lines.push(Line::from([vec![label("Committer")], person_spans(&detail.committer)].concat()));
+    lines.push(Line::default());
+    lines.extend(detail.message.trim_end().lines().map(|line| Line::from(line.to_string())));
+
+    let total = detail
+        .files
+        .iter()
+        .filter_map(|file| file.stats)
+        .fold(LineStats::default(), |total, stats| total + stats);
+    let items: Vec<ListItem> = detail.files.iter().map(file_status_item).collect();
+    let files_title = format!(" Files ({}) ", items.len());
+
+    let inner_width = area.width.saturating_sub(2).max(1) as usize;
+    let wrapped: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(inner_width).max(1))
+        .sum();
+    let info_height = (wrapped as u16 + 2).min(area.height / 2);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(info_height), Constraint::Min(3)])
+        .split(area);
+
+    let info_title = if app.is_loading_commit() {
+        " Commit loading… "
+    } else {
+        " Commit "
+    };
+    let info = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(info_title)
+            .title_style(Style::default().fg(Color::Magenta)),
+    );
+    f.render_widget(info, chunks[0]);
+
+    let list = List::new(items).block(
+        panel_block(app, Panel::CommitFiles)
+            .title(files_title)
+            .title_style(Style::default().fg(Color::Green))
+            .title_bottom(line_stats_line(total)),
+    );
+    draw_list(f, chunks[1], app, Panel::CommitFiles, list, 1);
+}
+
+// Name, email and time, in the person's own timezone.
+fn person_spans(person: &Person) -> Vec<Span<'static>> {
+    vec![
+        Span::styled(
+            format!("{} <{}>", person.name, person.email),
+            Style::default().fg(Color::Green),
+        ),
+        Span::raw(" "),
+        Span::styled(
+            person.time.format("%Y-%m-%d %H:%M:%S %z").to_string(),
+            Style::default().fg(Color::Gray),
+        ),
+    ]
+}
+
+fn draw_branches(f: &mut Frame, area: Rect, app: &mut App) {
+    let branches = &app.snapshot().branches;
+    let name_width = branches.iter().map(|branch| branch.name.chars().count()).max().unwrap_or(0);
+    let upstream_width = branches
+        .iter()
+        .map(|branch| branch.upstream.as_ref().map_or(0, |upstream| upstream.chars().count()))
+        .max()
+        .unwrap_or(0);
+
+    let items: Vec<ListItem> = branches
+        .iter()
+        .map(|branch| {
+            let (marker, name_style) = if branch.is_head {
+                ("* ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+            } else {
+                ("  ", Style::default())
+            };
+            let mut spans = vec![
+                Span::styled(marker, name_style),
+                Span::styled(format!("{:<width$}", branch.name, width = name_width), name_style),
+                Span::raw("  "),
+                Span::styled(
+                    format!(
+                        "{:<width$}",
+                        branch.upstream.as_deref().unwrap_or(""),
+                        width = upstream_width
+                    ),
+                    Style::default().fg(Color::Blue),
+                ),
+                Span::raw(" "),
+            ];
+
+            let tracking = if branch.upstream_gone {
+                Span::styled(format!("{:<9}", "gone"), Style::default().fg(Color::Red))
+            } else if branch.ahead > 0 || branch.behind > 0 {
+                Span::styled(
+                    format!("{:<9}", format!("↑{} ↓{}", branch.ahead, branch.behind)),
+                    Style::default().fg(Color::Yellow),
+                )
+            } else {
+                Span::raw(" ".repeat(9))
+            };
+            spans.push(tracking);
+            spans.push(Span::styled(
+                if branch.merged { "merged " } else { "       " },
+                Style::default().fg(Color::DarkGray),
+            ));
+            spans.push(Span::styled(
+                branch.last_commit_time.format("%Y-%m-%d %H:%M").to_string(),
+                Style::default().fg(Color::Gray),
+            ));
+            spans.push(Span::raw(" "));
+            spans.push(Span::raw(branch.last_commit_summary.clone()));
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
-    let commits_list = List::new(commit_items)
+    let title = format!(" Branches ({}) ", items.len());
+    let list = List::new(items).block(
+        panel_block(app, Panel::Branches)
+            .title(title)
+            .title_style(Style::default().fg(Color::Green)),
+    );
+
+    draw_list(f, area, app, Panel::Branches, list, 1);
+}
+
+/// The tags on the left, the selected one's details on the right.
+fn draw_tags(f: &mut Frame, area: Rect, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    let tags = &app.snapshot().tags;
+    let name_width = tags.iter().map(|tag| tag.name.chars().count()).max().unwrap_or(0);
+    let items: Vec<ListItem> = tags
+        .iter()
+        .map(|tag| {
+            let subject = match &tag.message {
+                Some(message) => message.lines().next().unwrap_or_default().to_string(),
+                None => tag.target_summary.clone(),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}", tag.name, width = name_width),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(" "),
+                Span::styled(tag.time.format("%Y-%m-%d").to_string(), Style::default().fg(Color::Gray)),
+                Span::raw(" "),
+                Span::styled(format!("{:>6}", describe(tag.ahead)), Style::default().fg(Color::Cyan)),
+                Span::raw(" "),
+                Span::raw(subject),
+            ]))
+        })
+        .collect();
+
+    let order = match app.tag_order() {
+        TagOrder::Version => "by version",
+        TagOrder::Date => "by date",
+    };
+    let title = format!(" Tags ({}, {}) ", items.len(), order);
+    let list = List::new(items).block(
+        panel_block(app, Panel::Tags)
+            .title(title)
+            .title_style(Style::default().fg(Color::Yellow)),
+    );
+    draw_list(f, chunks[0], app, Panel::Tags, list, 1);
+
+    let Some(tag) = app.selected_tag() else {
+        return;
+    };
+    let label = |text: &str| Span::styled(format!("{:<10}", text), Style::default().fg(Color::Gray));
+    let kind = if tag.annotated { "annotated" } else { "lightweight" };
+    let mut lines = vec![
+        Line::from(vec![
+            label("Tag"),
+            Span::styled(tag.name.clone(), Style::default().fg(Color::Yellow)),
+            Span::styled(format!(" ({})", kind), Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(vec![
+            label("Target"),
+            Span::styled(format!("{:.8}", tag.target), Style::default().fg(Color::Yellow)),
+            Span::raw(" "),
+            Span::raw(tag.target_summary.clone()),
+        ]),
+    ];
+    if let Some(tagger) = &tag.tagger {
+        lines.push(Line::from([vec![label("Tagger")], person_spans(tagger)].concat()));
+    }
+    let position = match tag.ahead {
+        Some(0) => "at HEAD".to_string(),
+        Some(1) => "HEAD is 1 commit past it".to_string(),
+        Some(ahead) => format!("HEAD is {} commits past it", ahead),
+        None => "not in HEAD's history".to_string(),
+    };
+    lines.push(Line::from(vec![label("Describe"), Span::raw(position)]));
+    if let Some(message) = &tag.message {
+        lines.push(Line::default());
+        lines.extend(message.lines().map(|line| Line::from(line.to_string())));
+    }
+
+    let details = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Tag ")
+            .title_style(Style::default().fg(Color::Magenta)),
+    );
+    f.render_widget(details, chunks[1]);
+}
+
+// Where HEAD stands relative to a tag, the way `git describe` counts it.
+fn describe(ahead: Option<usize>) -> String {
+    match ahead {
+        Some(0) => "HEAD".to_string(),
+        Some(ahead) => format!("+{}", ahead),
+        None => "-".to_string(),
+    }
+}
+
+fn draw_stashes(f: &mut Frame, area: Rect, app: &mut App) {
+    let stashes = &app.snapshot().stashes;
+    let branch_width = stashes
+        .iter()
+        .map(|stash| stash.branch.as_ref().map_or(0, |branch| branch.chars().count()))
+        .max()
+        .unwrap_or(0);
+    let now = Local::now();
+
+    let items: Vec<ListItem> = stashes
+        .iter()
+        .map(|stash| {
+            let mut spans = vec![
+                Span::styled(
+                    format!("{:<10}", format!("stash@{{{}}}", stash.index)),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    format!("{:>8}", age(now - stash.time)),
+                    Style::default().fg(Color::Gray),
+                ),
+                Span::raw("  "),
+                Span::styled(
+                    format!(
+                        "{:<width$}",
+                        stash.branch.as_deref().unwrap_or(""),
+                        width = branch_width
+                    ),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::raw(" "),
+            ];
+            if stash.untracked {
+                spans.push(Span::styled("+untracked ", Style::default().fg(Color::DarkGray)));
+            }
+            spans.push(Span::raw(stash.message.clone()));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let title = format!(" Stash ({}) ", items.len());
+    let list = List::new(items).block(
+        panel_block(app, Panel::Stashes)
+            .title(title)
+            .title_style(Style::default().fg(Color::Magenta)),
+    );
+
+    draw_list(f, area, app, Panel::Stashes, list, 1);
+}
+
+// How long ago something happened, in its largest whole unit.
+fn age(elapsed: chrono::Duration) -> String {
+    let minutes = elapsed.num_minutes().max(0);
+    match minutes {
+        0 => "just now".to_string(),
+        1..=59 => format!("{}m ago", minutes),
+        60..=1439 => format!("{}h ago", minutes / 60),
+        1440..=43199 => format!("{}d ago", minutes / 1440),
+        43200..=525599 => format!("{}mo ago", minutes / 43200),
+        _ => format!("{}y ago", minutes / 525600),
+    }
+}
+
+fn draw_discards(f: &mut Frame, area: Rect, app: &mut App) {
+    let items: Vec<ListItem> = app
+        .discards()
+        .iter()
+        .map(|record| {
+            let paths = record
+                .paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    record.time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    Style::default().fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled(format!("{:.8}", record.id), Style::default().fg(Color::Yellow)),
+                Span::raw(" "),
+                Span::raw(paths),
+            ]))
+        })
+        .collect();
+
+    let title = format!(" Recently Discarded ({}) ", items.len());
+    let list = List::new(items).block(
+        panel_block(app, Panel::Discards)
+            .title(title)
+            .title_style(Style::default().fg(Color::Red)),
+    );
+
+    draw_list(f, area, app, Panel::Discards, list, 1);
+}
+
+/// A yes/no prompt over the middle of the screen.
+fn draw_confirmation(f: &mut Frame, area: Rect, prompt: &str) {
+    let width = area.width.saturating_sub(4).min(70);
+    let text = vec![
+        Line::from(prompt.to_string()),
+        Line::default(),
+        key_hints(&[("y", "yes"), ("n", "no")]),
+    ];
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let height = (prompt.chars().count().div_ceil(inner_width) as u16 + 4).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let dialog = Paragraph::new(text)
+        .wrap(Wrap { trim: true })
+        .alignment(Alignment::Center)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" Recent Commits ")
-                .title_style(Style::default().fg(Color::Magenta)),
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(" Confirm ")
+                .title_style(Style::default().fg(Color::Yellow)),
         );
-
-    f.render_widget(commits_list, area);
-    Ok(())
+    f.render_widget(Clear, popup);
+    f.render_widget(dialog, popup);
 }
 
-fn draw_footer(f: &mut Frame, area: Rect) {
-    let footer_text = Line::from(vec![
-        Span::raw("Press "),
-        Span::styled("'q'", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(" to quit, "),
-        Span::styled("'r'", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(" to refresh, "),
-        Span::styled("'Tab'", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(" to switch tabs"),
-    ]);
+fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
+    if let Some((label, input)) = app.prompt() {
+        let width = (label.chars().count() + input.chars().count()) as u16;
+        let prompt = Line::from(vec![
+            Span::styled(label, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(input.to_string()),
+        ]);
+        f.render_widget(
+            Paragraph::new(prompt).style(Style::default().bg(Color::DarkGray)),
+            area,
+        );
+        f.set_cursor_position((area.x + width.min(area.width.saturating_sub(1)), area.y));
+        return;
+    }
+
+    let footer_text = if let Some(error) = app.last_error() {
+        Line::from(Span::styled(error, Style::default().fg(Color::Red)))
+    } else if let Some(warning) = app.warning() {
+        Line::from(Span::styled(warning, Style::default().fg(Color::Yellow)))
+    } else {
+        help_line(app)
+    };
 
     let footer = Paragraph::new(footer_text)
         .style(Style::default().bg(Color::DarkGray))
@@ -231,12 +1021,109 @@ fn draw_footer(f: &mut Frame, area: Rect) {
     f.render_widget(footer, area);
 }
 
+fn help_line(app: &App) -> Line<'static> {
+    let mut keys = vec![
+        ("q", "quit"),
+        ("r", "refresh"),
+        ("Tab", "tabs"),
+        ("←/→", "focus"),
+        ("↑/↓", "select"),
+    ];
+    if *app.current_tab() == TabType::Status {
+        if app.focus() == Panel::Diff {
+            keys.push(("s/u", "stage/unstage hunk"));
+            keys.push(("Space", "select lines"));
+            keys.push(("[/]", "hunks"));
+        } else {
+            keys.push(("c", "combined"));
+            keys.push(("s/u", "stage/unstage"));
+            keys.push(("S/U", "directory"));
+            keys.push(("a/A", "all"));
+        }
+        keys.push(("d", "discard"));
+        keys.push(("C", "commit"));
+        if app.visible_panels().contains(&Panel::Diff) {
+            keys.push(("v", "layout"));
+            keys.push(("Esc", "close diff"));
+        } else {
+            keys.push(("Enter", "diff"));
+        }
+    }
+    if *app.current_tab() == TabType::Commits {
+        match app.focus() {
+            Panel::Commits => {
+                keys.push(("Enter", "details"));
+                keys.push(("w", "HEAD/all refs"));
+                keys.push(("b", "branch"));
+                keys.push(("t", "tag"));
+                keys.push(("/", "search"));
+                if app.commit_filter().is_some() {
+                    keys.push(("n/N", "matches"));
+                    if app.commit_detail().is_none() {
+                        keys.push(("Esc", "clear search"));
+                    }
+                }
+            }
+            Panel::CommitFiles => keys.push(("Enter", "diff")),
+            Panel::Diff => keys.push(("v", "layout")),
+            _ => {}
+        }
+        if app.commit_detail().is_some() {
+            keys.push(("Esc", "close"));
+        }
+    }
+    if *app.current_tab() == TabType::Branches {
+        keys.push(("Enter", "checkout"));
+        keys.push(("n", "new"));
+        keys.push(("m", "rename"));
+        keys.push(("d", "delete"));
+    }
+    if *app.current_tab() == TabType::Tags {
+        keys.push(("o", "version/date order"));
+        keys.push(("n", "tag HEAD"));
+        keys.push(("d", "delete"));
+    }
+    if *app.current_tab() == TabType::Stash {
+        if app.focus() == Panel::Diff {
+            keys.push(("v", "layout"));
+            keys.push(("Esc", "close"));
+        } else {
+            keys.push(("Enter", "preview"));
+            keys.push(("s/S", "push/with untracked"));
+            keys.push(("a", "apply"));
+            keys.push(("p", "pop"));
+            keys.push(("d", "drop"));
+        }
+    }
+    if *app.current_tab() == TabType::Discards {
+        keys.push(("Enter", "restore"));
+    }
+    key_hints(&keys)
+}
+
+fn key_hints(keys: &[(&str, &str)]) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (i, (key, action)) in keys.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(
+            key.to_string(),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(format!(" {}", action)));
+    }
+    Line::from(spans)
+}
+
 fn get_status_symbol(status: &FileStatusType) -> &'static str {
     match status {
         FileStatusType::Added => "+",
         FileStatusType::Modified => "M",
         FileStatusType::Deleted => "-",
         FileStatusType::Renamed => "R",
+        FileStatusType::Copied => "C",
+        FileStatusType::TypeChanged => "T",
         FileStatusType::Untracked => "?",
         FileStatusType::Conflicted => "!",
     }
@@ -247,7 +1134,8 @@ fn get_status_color(status: &FileStatusType) -> Color {
         FileStatusType::Added => Color::Green,
         FileStatusType::Modified => Color::Yellow,
         FileStatusType::Deleted => Color::Red,
-        FileStatusType::Renamed => Color::Blue,
+        FileStatusType::Renamed | FileStatusType::Copied => Color::Blue,
+        FileStatusType::TypeChanged => Color::Magenta,
         FileStatusType::Untracked => Color::Gray,
         FileStatusType::Conflicted => Color::Red,
     }
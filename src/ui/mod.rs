@@ -1,5 +1,8 @@
 pub mod app;
+pub mod commit_editor;
+pub mod diff_view;
 pub mod events;
 pub mod layout;
+pub mod selection;
 
-pub use app::App;
\ No newline at end of file
+pub use app::{App, AppOptions};
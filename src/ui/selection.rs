@@ -0,0 +1,97 @@
+use std::ops::Range;
+
+use ratatui::widgets::ListState;
+
+/// Cursor and scroll position of a list panel, kept in bounds as the
+/// underlying data changes between refreshes.
+#[derive(Debug, Default)]
+pub struct ListSelection {
+    state: ListState,
+    len: usize,
+    page_size: usize,
+}
+
+impl ListSelection {
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        let selected = match self.state.selected() {
+            _ if len == 0 => None,
+            Some(index) => Some(index.min(len - 1)),
+            None => Some(0),
+        };
+        self.state.select(selected);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if self.len > 0 {
+            self.state.select(Some(index.min(self.len - 1)));
+        }
+    }
+
+    /// Number of items that fit in the panel, as measured by the last draw.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size.max(1);
+    }
+
+    /// The items in view, scrolled just far enough to show the selection,
+    /// for lists too long to build every item of on each draw.
+    pub fn visible_range(&mut self) -> Range<usize> {
+        let selected = self.state.selected().unwrap_or(0);
+        let offset = self
+            .state
+            .offset()
+            .min(selected)
+            .max((selected + 1).saturating_sub(self.page_size));
+        *self.state.offset_mut() = offset;
+        offset..(offset + self.page_size).min(self.len)
+    }
+
+    pub fn next(&mut self) {
+        self.move_by(1);
+    }
+
+    pub fn previous(&mut self) {
+        self.move_by(-1);
+    }
+
+    pub fn page_down(&mut self) {
+        self.move_by(self.page_size as isize);
+    }
+
+    pub fn page_up(&mut self) {
+        self.move_by(-(self.page_size as isize));
+    }
+
+    pub fn first(&mut self) {
+        self.select(0);
+    }
+
+    pub fn last(&mut self) {
+        self.select(self.len.saturating_sub(1));
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if self.len == 0 {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let target = (current + delta).clamp(0, self.len as isize - 1);
+        self.state.select(Some(target as usize));
+    }
+
+    pub fn state_mut(&mut self) -> &mut ListState {
+        &mut self.state
+    }
+}
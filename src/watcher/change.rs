@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use crate::git::snapshot::RefreshScope;
+
+/// What a batch of filesystem events means for the repository, derived from
+/// which files under `.git` (or in the work tree) were touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GitChangeEvent {
+    IndexChanged,
+    HeadMoved,
+    NewCommit,
+    RefsChanged,
+    RefsFetched,
+    StashChanged,
+    OperationChanged,
+    WorkingTreeChanged,
+}
+
+impl GitChangeEvent {
+    /// Classifies a path relative to the git directory. `head_ref` is the
+    /// full name of the branch HEAD points at, if any.
+    pub fn from_git_path(relative: &Path, head_ref: Option<&str>) -> Option<Self> {
+        let path = relative.to_string_lossy();
+        let path = path.as_ref();
+
+        if path.ends_with(".lock") {
+            return None;
+        }
+
+        match path {
+            "index" => return Some(Self::IndexChanged),
+            "HEAD" => return Some(Self::HeadMoved),
+            "FETCH_HEAD" => return Some(Self::RefsFetched),
+            "packed-refs" => return Some(Self::RefsChanged),
+            "refs/stash" | "logs/refs/stash" => return Some(Self::StashChanged),
+            "MERGE_HEAD" | "CHERRY_PICK_HEAD" | "REVERT_HEAD" | "BISECT_LOG" => {
+                return Some(Self::OperationChanged)
+            }
+            _ => {}
+        }
+
+        if ["rebase-merge", "rebase-apply", "sequencer"]
+            .iter()
+            .any(|dir| relative.starts_with(dir))
+        {
+            Some(Self::OperationChanged)
+        } else if relative.starts_with("refs/heads") {
+            if head_ref == Some(path) {
+                Some(Self::NewCommit)
+            } else {
+                Some(Self::RefsChanged)
+            }
+        } else if relative.starts_with("refs/remotes") {
+            Some(Self::RefsFetched)
+        } else if relative.starts_with("refs") {
+            Some(Self::RefsChanged)
+        } else {
+            // objects/, logs/, COMMIT_EDITMSG and friends follow one of the
+            // changes above and carry no information of their own.
+            None
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::IndexChanged => "index changed",
+            Self::HeadMoved => "HEAD moved",
+            Self::NewCommit => "new commit",
+            Self::RefsChanged => "refs updated",
+            Self::RefsFetched => "fetched",
+            Self::StashChanged => "stash changed",
+            Self::OperationChanged => "merge/rebase state changed",
+            Self::WorkingTreeChanged => "files edited",
+        }
+    }
+
+    pub fn refresh_scope(&self) -> RefreshScope {
+        match self {
+            Self::IndexChanged | Self::WorkingTreeChanged => RefreshScope {
+                status: true,
+                ..RefreshScope::NONE
+            },
+            Self::StashChanged => RefreshScope {
+                status: true,
+                refs: true,
+                ..RefreshScope::NONE
+            },
+            // Branches moving change the graph and its ref labels too.
+            Self::RefsChanged | Self::RefsFetched => RefreshScope {
+                commits: true,
+                refs: true,
+                ..RefreshScope::NONE
+            },
+            Self::HeadMoved | Self::NewCommit | Self::OperationChanged => RefreshScope::ALL,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use GitChangeEvent::*;
+
+    #[test]
+    fn git_paths_map_to_changes() {
+        let cases = [
+            ("HEAD", Some(HeadMoved)),
+            ("index", Some(IndexChanged)),
+            ("index.lock", None),
+            ("packed-refs", Some(RefsChanged)),
+            ("FETCH_HEAD", Some(RefsFetched)),
+            ("MERGE_HEAD", Some(OperationChanged)),
+            ("rebase-merge/git-rebase-todo", Some(OperationChanged)),
+            ("rebase-merge/msgnum", Some(OperationChanged)),
+            ("sequencer/todo", Some(OperationChanged)),
+            ("refs/heads/main", Some(NewCommit)),
+            ("refs/heads/topic", Some(RefsChanged)),
+            ("refs/heads/main.lock", None),
+            ("refs/remotes/origin/main", Some(RefsFetched)),
+            ("refs/tags/v1.0", Some(RefsChanged)),
+            ("refs/stash", Some(StashChanged)),
+            ("logs/refs/stash", Some(StashChanged)),
+            ("logs/HEAD", None),
+            ("objects/1a/2b3c4d", None),
+            ("COMMIT_EDITMSG", None),
+            // Another worktree's HEAD and index.
+            ("worktrees/feature/HEAD", None),
+            ("worktrees/feature/index", None),
+        ];
+        for (path, change) in cases {
+            assert_eq!(
+                GitChangeEvent::from_git_path(Path::new(path), Some("refs/heads/main")),
+                change,
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn a_detached_head_has_no_branch_to_commit_to() {
+        assert_eq!(
+            GitChangeEvent::from_git_path(Path::new("refs/heads/main"), None),
+            Some(RefsChanged)
+        );
+    }
+}
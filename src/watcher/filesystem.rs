@@ -1,35 +1,228 @@
 use anyhow::Result;
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
-use std::sync::mpsc::{channel, Receiver};
+use git2::Repository;
+use notify::event::ModifyKind;
+use notify::{
+    Config, ErrorKind, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::time::Duration;
 
+use crate::watcher::change::GitChangeEvent;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How the watcher learns about changes: kernel notifications (inotify,
+/// FSEvents, ...) or periodic scans, which also work on NFS and bind mounts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WatchMode {
+    #[default]
+    Native,
+    Poll(Duration),
+}
+
+/// A summary of what the watcher is currently covering, for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchStatus {
+    pub mode: WatchMode,
+    pub watches: usize,
+    /// Whether work-tree changes go unnoticed because the watch limit was
+    /// exhausted; only `.git` is watched and the caller should poll status.
+    pub degraded: bool,
+}
+
 pub struct FileSystemWatcher {
-    watcher: RecommendedWatcher,
+    watcher: Box<dyn Watcher + Send>,
+    mode: WatchMode,
+    tx: Sender<notify::Result<Event>>,
     rx: Receiver<notify::Result<Event>>,
+    repo: Option<Repository>,
+    workdir: Option<PathBuf>,
+    git_dir: Option<PathBuf>,
+    watched: HashSet<PathBuf>,
+    degraded: bool,
 }
 
 impl FileSystemWatcher {
-    pub fn new() -> Result<Self> {
+    pub fn new(mode: WatchMode) -> Result<Self> {
         let (tx, rx) = channel();
-        
-        let watcher = RecommendedWatcher::new(
-            move |res| {
-                let _ = tx.send(res);
-            },
-            Config::default().with_poll_interval(Duration::from_secs(1)),
-        )?;
+        let watcher = Self::create_watcher(mode, tx.clone())?;
 
-        Ok(Self { watcher, rx })
+        Ok(Self {
+            watcher,
+            mode,
+            tx,
+            rx,
+            repo: None,
+            workdir: None,
+            git_dir: None,
+            watched: HashSet::new(),
+            degraded: false,
+        })
     }
 
     pub fn watch(&mut self, path: &Path) -> Result<()> {
-        self.watcher.watch(path, RecursiveMode::Recursive)?;
+        // Event paths are reported relative to the watched path, so watch the
+        // canonical one to be able to match them against the work tree.
+        let path = path.canonicalize()?;
+        self.repo = Repository::discover(&path).ok();
+        self.workdir = self
+            .repo
+            .as_ref()
+            .and_then(|repo| repo.workdir())
+            .and_then(|workdir| workdir.canonicalize().ok());
+        self.git_dir = self
+            .repo
+            .as_ref()
+            .and_then(|repo| repo.path().canonicalize().ok());
+
+        let Some(git_dir) = self.git_dir.clone() else {
+            self.watcher.watch(&path, RecursiveMode::Recursive)?;
+            self.watched.insert(path);
+            return Ok(());
+        };
+
+        match self.watch_tree(&git_dir) {
+            Err(e) if self.mode == WatchMode::Native && Self::is_watch_limit(&e) => {
+                // Not even .git fits: replacing the native watcher releases
+                // whatever it managed to register, then poll everything.
+                self.mode = WatchMode::Poll(DEFAULT_POLL_INTERVAL);
+                self.watcher = Self::create_watcher(self.mode, self.tx.clone())?;
+                self.watched.clear();
+                self.watch_tree(&git_dir)?;
+            }
+            result => result?,
+        }
+
+        if let Some(workdir) = self.workdir.clone() {
+            self.watch_workdir_tree(&workdir)?;
+        }
         Ok(())
     }
 
+    pub fn status(&self) -> WatchStatus {
+        WatchStatus {
+            mode: self.mode,
+            watches: self.watched.len(),
+            degraded: self.degraded,
+        }
+    }
+
+    fn watch_workdir_tree(&mut self, root: &Path) -> Result<()> {
+        match self.watch_tree(root) {
+            Err(e) if Self::is_watch_limit(&e) => {
+                self.degrade();
+                Ok(())
+            }
+            result => Ok(result?),
+        }
+    }
+
+    // One non-recursive watch per directory, so ignored trees such as
+    // `target/` or `node_modules/` never consume watches.
+    fn watch_tree(&mut self, root: &Path) -> notify::Result<()> {
+        let mut pending = vec![root.to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            if self.watched.contains(&dir) {
+                continue;
+            }
+            self.watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+            if let Ok(entries) = fs::read_dir(&dir) {
+                pending.extend(
+                    entries
+                        .flatten()
+                        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                        .map(|entry| entry.path())
+                        .filter(|path| self.should_watch_dir(path)),
+                );
+            }
+            self.watched.insert(dir);
+        }
+        Ok(())
+    }
+
+    fn should_watch_dir(&self, path: &Path) -> bool {
+        if let Some(relative) = self
+            .git_dir
+            .as_deref()
+            .and_then(|git_dir| path.strip_prefix(git_dir).ok())
+        {
+            // Refs, and the state of a rebase, cherry-pick or revert under
+            // way, whose progress and todo list change without touching any
+            // file directly inside .git. Everything else (HEAD, index,
+            // MERGE_HEAD, ...) is reported through those files, except for
+            // dropping a stash other than the newest, which only rewrites
+            // `logs/refs/stash`; of the reflogs, that's the one watched.
+            return ["refs", "rebase-merge", "rebase-apply", "sequencer"]
+                .iter()
+                .any(|dir| relative.starts_with(dir))
+                || relative == Path::new("logs")
+                || relative == Path::new("logs/refs");
+        }
+        if self.degraded || Self::is_in_git_dir(path) {
+            return false;
+        }
+
+        let Some(repo) = &self.repo else {
+            return false;
+        };
+        self.workdir
+            .as_deref()
+            .and_then(|workdir| path.strip_prefix(workdir).ok())
+            .is_some_and(|relative| !repo.is_path_ignored(relative).unwrap_or(true))
+    }
+
+    fn degrade(&mut self) {
+        self.degraded = true;
+
+        let git_dir = self.git_dir.clone().unwrap_or_default();
+        let workdir_watches: Vec<PathBuf> = self
+            .watched
+            .iter()
+            .filter(|dir| !dir.starts_with(&git_dir))
+            .cloned()
+            .collect();
+
+        for dir in workdir_watches {
+            let _ = self.watcher.unwatch(&dir);
+            self.watched.remove(&dir);
+        }
+    }
+
+    fn create_watcher(
+        mode: WatchMode,
+        tx: Sender<notify::Result<Event>>,
+    ) -> Result<Box<dyn Watcher + Send>> {
+        let handler = move |res| {
+            let _ = tx.send(res);
+        };
+
+        Ok(match mode {
+            WatchMode::Native => Box::new(RecommendedWatcher::new(handler, Config::default())?),
+            WatchMode::Poll(interval) => Box::new(PollWatcher::new(
+                handler,
+                Config::default().with_poll_interval(interval),
+            )?),
+        })
+    }
+
+    fn is_watch_limit(error: &notify::Error) -> bool {
+        match &error.kind {
+            ErrorKind::MaxFilesWatch => true,
+            // EMFILE / ENOSPC from backends that don't map them.
+            ErrorKind::Io(e) => matches!(e.raw_os_error(), Some(24) | Some(28)),
+            _ => false,
+        }
+    }
+
     pub fn unwatch(&mut self, path: &Path) -> Result<()> {
-        self.watcher.unwatch(path)?;
+        let path = path.canonicalize()?;
+        self.watcher.unwatch(&path)?;
+        self.watched.remove(&path);
         Ok(())
     }
 
@@ -40,26 +233,84 @@ impl FileSystemWatcher {
         }
     }
 
-    pub fn has_git_changes(&self) -> bool {
+    /// Drains all pending notify events and classifies them, extending the
+    /// watches to directories that were created or moved in.
+    pub fn drain_changes(&mut self) -> BTreeSet<GitChangeEvent> {
+        let mut changes = BTreeSet::new();
+        let head_ref = self.head_ref();
+
         while let Some(event) = self.try_recv_event() {
-            if Self::is_git_related(&event) {
-                return true;
+            match event.kind {
+                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                    self.watch_new_dirs(&event.paths);
+                }
+                EventKind::Remove(_) => {
+                    // The watches on a deleted tree go with it.
+                    for path in &event.paths {
+                        self.watched.retain(|dir| !dir.starts_with(path));
+                    }
+                }
+                EventKind::Modify(_) => {}
+                _ => continue,
             }
+            changes.extend(
+                event
+                    .paths
+                    .iter()
+                    .filter_map(|path| self.classify_path(path, head_ref.as_deref())),
+            );
         }
-        false
+        changes
     }
 
-    fn is_git_related(event: &Event) -> bool {
-        match &event.kind {
-            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
-                event.paths.iter().any(|path| {
-                    path.components().any(|c| c.as_os_str() == ".git") ||
-                    path.extension().map_or(false, |ext| {
-                        ext == "rs" || ext == "toml" || ext == "md"
-                    })
-                })
+    fn watch_new_dirs(&mut self, paths: &[PathBuf]) {
+        if self.git_dir.is_none() {
+            return;
+        }
+
+        for path in paths {
+            if path.is_dir() && !self.watched.contains(path) && self.should_watch_dir(path) {
+                let _ = self.watch_workdir_tree(path);
             }
-            _ => false,
         }
     }
-}
\ No newline at end of file
+
+    fn head_ref(&self) -> Option<String> {
+        let head = self.repo.as_ref()?.find_reference("HEAD").ok()?;
+        head.symbolic_target().map(str::to_string)
+    }
+
+    fn classify_path(&self, path: &Path, head_ref: Option<&str>) -> Option<GitChangeEvent> {
+        let Some(repo) = &self.repo else {
+            return Some(GitChangeEvent::WorkingTreeChanged);
+        };
+
+        if let Some(relative) = self
+            .git_dir
+            .as_deref()
+            .and_then(|git_dir| path.strip_prefix(git_dir).ok())
+        {
+            return GitChangeEvent::from_git_path(relative, head_ref);
+        }
+        if Self::is_in_git_dir(path) {
+            return None;
+        }
+
+        let relative = self
+            .workdir
+            .as_deref()
+            .and_then(|workdir| path.strip_prefix(workdir).ok());
+
+        match relative {
+            Some(relative) if relative.as_os_str().is_empty() => None,
+            Some(relative) if repo.is_path_ignored(relative).unwrap_or(false) => None,
+            Some(_) => Some(GitChangeEvent::WorkingTreeChanged),
+            None => None,
+        }
+    }
+
+    // Nested repositories and submodules have their own `.git`.
+    fn is_in_git_dir(path: &Path) -> bool {
+        path.components().any(|c| c.as_os_str() == ".git")
+    }
+}
@@ -1,3 +1,5 @@
+pub mod change;
 pub mod filesystem;
 
-pub use filesystem::FileSystemWatcher;
\ No newline at end of file
+pub use change::GitChangeEvent;
+pub use filesystem::{FileSystemWatcher, WatchMode, WatchStatus};